@@ -1,11 +1,13 @@
-use std::{collections::HashMap, time::Duration};
-use std::{task::Poll, thread};
-use futures::Stream;
-use serde_json::{json, Value};
+use std::{collections::{HashMap, VecDeque}, time::Duration};
+use std::{future::Future, pin::Pin, task::{Context, Poll}};
+use futures::{ready, Stream};
+use futures_timer::Delay;
+use serde_json::Value;
 use unicode_segmentation::UnicodeSegmentation;
 
-use crate::json_stream_parser::parser_output::stream_protocol_output::STREAM_VAR_PREFIX;
-use crate::ref_index_generator::RefIndexGenerator;
+use crate::json_stream_parser::parser_output::stream_protocol_output::OPERATOR_CLOSE;
+use crate::{ref_index_generator::RefIndexGenerator, Shared};
+use crate::stream_protocol::{dialect::ProtocolDialect, protocol_row::{ProtocolRow, RowEncoder, RowValue}, replay_log::ReplayLog, sse::{SseFramer, SseStream}, text_row_encoder::TextRowEncoder, ProtocolHeader, EXTENSION_COMPLETION_MARKERS, EXTENSION_END_ROWS};
 
 use super::json_value_pointer::JsonValuePointer;
 
@@ -16,9 +18,14 @@ pub struct JsonProtocolChunker {
     source: Value,
     idx_generator: RefIndexGenerator, // A shared, dynamic counter for new rows
     root_ref_index: usize, // An initially assigned number for this chunker to start with
+    completion_markers: bool, // Whether to write a close row once a node is complete
+    end_row: bool, // Whether to write an end row after the last one
+    header: bool, // Whether to write the protocol header before the first row
+    dialect: ProtocolDialect,
 }
 
-pub struct JsonProtocolChunkIter {
+/// Iterates over the rows of a Value, before they are encoded
+pub struct JsonProtocolRowIter {
     source: Value,
     idx_generator: RefIndexGenerator,
     root_ref_index: usize,
@@ -28,17 +35,29 @@ pub struct JsonProtocolChunkIter {
     pointer: JsonValuePointer, // Points to the current node being iterated on
     pointer_index_map: HashMap<String, usize>, // Map of the ref_index assigned to the pointed level
     buf_size: usize,
+    completion_markers: bool,
+    end_row: bool,
+    pending_rows: VecDeque<ProtocolRow>, // Rows to yield on the next iterations, before going on
+    finished: bool,
+}
+
+/// Iterates over the rows of a Value, written as text
+pub struct JsonProtocolChunkIter {
+    rows: JsonProtocolRowIter,
+    encoder: TextRowEncoder,
+    replay_log: Option<ReplayLog>, // Records the rows yielded, for clients reconnecting mid-stream
 }
 
-#[allow(unused)]
 pub struct JsonProtocolChunkStream {
     chunker: JsonProtocolChunkIter,
     sleep_duration: Duration,
+    delay: Option<Delay>, // Wait before the next row, woken by the timer instead of blocking the thread
 }
 
 pub enum JsonProtocolChunkOperator {
     Assign,
-    Append
+    Append,
+    Close
 }
 
 impl JsonProtocolChunkOperator {
@@ -46,6 +65,7 @@ impl JsonProtocolChunkOperator {
         match self {
             JsonProtocolChunkOperator::Assign => "=",
             JsonProtocolChunkOperator::Append => "+=",
+            JsonProtocolChunkOperator::Close => OPERATOR_CLOSE,
         }
     }
 }
@@ -55,12 +75,77 @@ impl JsonProtocolChunker {
         JsonProtocolChunker {
             source,
             idx_generator,
-            root_ref_index
+            root_ref_index,
+            completion_markers: false,
+            end_row: false,
+            header: false,
+            dialect: ProtocolDialect::default()
+        }
+    }
+
+    /// Writes a close row (ex: `5!`) once a node is complete
+    /// Same rows as StreamProtocolOutput::with_completion_markers(), except that nested null, bool and number values
+    /// are closed too, as the chunker writes them under their own index
+    pub fn with_completion_markers(mut self) -> Self {
+        self.completion_markers = true;
+        self
+    }
+
+    /// Writes an end row (`!end`) after the last row, so that clients can tell a finished response from an interrupted one
+    /// Same row as StreamProtocolOutput::with_end_rows()
+    pub fn with_end_row(mut self) -> Self {
+        self.end_row = true;
+        self
+    }
+
+    /// Writes a header row before the first one, declaring the protocol version, the root index and the enabled extensions
+    /// Same row as StreamProtocolOutput::with_header()
+    pub fn with_header(mut self) -> Self {
+        self.header = true;
+        self
+    }
+
+    /// Configures the reference prefix, the row terminator, the encoding of indices and the escaping of literal strings
+    /// Same dialect as StreamProtocolOutput::with_dialect()
+    pub fn with_dialect(mut self, dialect: ProtocolDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Header describing the rows written by this chunker
+    pub fn protocol_header(&self) -> ProtocolHeader {
+        TextRowEncoder::new(self.dialect.clone()).complete_header(&self.row_header())
+    }
+
+    /// Header describing the rows, regardless of the way they are encoded
+    fn row_header(&self) -> ProtocolHeader {
+        let mut header = ProtocolHeader::new(self.root_ref_index);
+        if self.completion_markers {
+            header = header.with_extension(EXTENSION_COMPLETION_MARKERS);
+        }
+        if self.end_row {
+            header = header.with_extension(EXTENSION_END_ROWS);
         }
+        header
+    }
+
+    /// Structured rows, to be encoded by the caller (see TextRowEncoder). The dialect is not used
+    pub fn rows(self, buf_size: usize) -> JsonProtocolRowIter {
+        let header = self.header.then(|| self.row_header());
+        let mut iter = JsonProtocolRowIter::new(self.source, buf_size, self.idx_generator.clone(), self.root_ref_index);
+        iter.completion_markers = self.completion_markers;
+        iter.end_row = self.end_row;
+        iter.pending_rows.extend(header.map(ProtocolRow::header));
+        iter
     }
 
-    pub fn chunks(self, buf_size: usize) -> JsonProtocolChunkIter {
-        JsonProtocolChunkIter::new(self.source, buf_size, self.idx_generator.clone(), self.root_ref_index)
+    pub fn chunks(mut self, buf_size: usize) -> JsonProtocolChunkIter {
+        let encoder = TextRowEncoder::new(std::mem::take(&mut self.dialect));
+        JsonProtocolChunkIter {
+            rows: self.rows(buf_size),
+            encoder,
+            replay_log: None
+        }
     }
 
     pub fn stream(self, buf_size: usize, sleep_interval: usize) -> JsonProtocolChunkStream {
@@ -69,6 +154,7 @@ impl JsonProtocolChunker {
         JsonProtocolChunkStream {
             chunker,
             sleep_duration: Duration::from_millis(sleep_interval.try_into().unwrap()),
+            delay: None,
         }
     }
 }
@@ -76,6 +162,27 @@ impl JsonProtocolChunker {
 impl JsonProtocolChunkIter {
     pub fn new(source: Value, buf_size: usize, idx_generator: RefIndexGenerator, root_ref_index: usize) -> Self {
         JsonProtocolChunkIter {
+            rows: JsonProtocolRowIter::new(source, buf_size, idx_generator, root_ref_index),
+            encoder: TextRowEncoder::default(),
+            replay_log: None
+        }
+    }
+
+    /// Records every row yielded into the log, to be replayed to a client reconnecting mid-stream
+    /// When the log replies Replay::Resync, the whole value can be chunked again instead
+    pub fn with_replay_log(mut self, replay_log: ReplayLog) -> Self {
+        self.replay_log = Some(replay_log);
+        self
+    }
+
+    pub fn replay_log(&self) -> Option<&ReplayLog> {
+        self.replay_log.as_ref()
+    }
+}
+
+impl JsonProtocolRowIter {
+    pub fn new(source: Value, buf_size: usize, idx_generator: RefIndexGenerator, root_ref_index: usize) -> Self {
+        JsonProtocolRowIter {
             // Init with empty value of the same type
             source,
             pointer: JsonValuePointer {
@@ -86,16 +193,68 @@ impl JsonProtocolChunkIter {
             current_ref_index: Some(0),
             buf_size,
             idx_generator,
-            root_ref_index
+            root_ref_index,
+            completion_markers: false,
+            end_row: false,
+            pending_rows: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    /// Called once every element of the pointed node has been written
+    /// Either goes up one level, or terminates if at root
+    fn end_node(&mut self, pointer_expr: &str, current_idx: usize) -> Option<ProtocolRow> {
+        self.next_accessed_idx.remove(pointer_expr);
+        if pointer_expr == "/" {
+            self.finished = true;
+            if self.completion_markers {
+                // The root is always closed, whatever its type
+                self.pending_rows.push_back(ProtocolRow::close(current_idx));
+            }
+            if self.end_row {
+                self.pending_rows.push_back(ProtocolRow::end());
+            }
+            return self.pending_rows.pop_front();
         }
+        self.pointer.up();
+        if self.completion_markers {
+            return Some(ProtocolRow::close(current_idx));
+        }
+        self.next()
+    }
+
+    /// Close row to write next for a node left as soon as it was written (short string, empty array or object)
+    fn close_on_next(&self, pointer_expr: &str, current_idx: usize) -> Option<ProtocolRow> {
+        // The root is closed by end_node, when coming back to it
+        (self.completion_markers && pointer_expr != "/").then(|| ProtocolRow::close(current_idx))
     }
 }
 
 /// The iterator's Item is a partial row to be printed as the next yield following the protocol's definition
 impl Iterator for JsonProtocolChunkIter {
     type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.rows.next()?;
+        let mut output = String::new();
+        self.encoder.encode_row(&row, &mut output);
+        if let Some(replay_log) = self.replay_log.as_mut() {
+            replay_log.record(output.as_str());
+        }
+        Some(output)
+    }
+}
+
+impl Iterator for JsonProtocolRowIter {
+    type Item = ProtocolRow;
     
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(row) = self.pending_rows.pop_front() {
+            return Some(row);
+        }
+        if self.finished {
+            return None;
+        }
         // Compare source and current values at location pointed at
         let source_val: &Value = match &self.pointer.pointer_expr {
             Some(p) => {
@@ -116,7 +275,7 @@ impl Iterator for JsonProtocolChunkIter {
             self.current_ref_index = Some(self.idx_generator.generate());
         }
 
-        let current_idx = self
+        let current_idx = *self
             .pointer_index_map
             .entry(pointer_expr.clone())
             .or_insert(
@@ -127,39 +286,23 @@ impl Iterator for JsonProtocolChunkIter {
 
         match source_val {
             Value::Null | Value::Bool(_) | Value::Number(_) => {
-                let next_accessed = self.next_accessed_idx.get(pointer_expr.as_str()).map(|v| *v);
+                let next_accessed = self.next_accessed_idx.get(pointer_expr.as_str()).copied();
                 if next_accessed.is_some() {
                     // Null/Bool/Number processing ended : either go up one level, or terminate if at root
                     // Also cleanup next_accessed_idx
-                    self.next_accessed_idx.remove(pointer_expr.as_str());
-                    if pointer_expr.as_str() == "/" {
-                        return None;
-                    } else {
-                        self.pointer.up();
-                        return self.next();
-                    }
+                    return self.end_node(&pointer_expr, current_idx);
                 } else {
                     self.next_accessed_idx.insert(pointer_expr, 0); // Doesn't matter the value, just mark this node as processed
-                    Some(format!("{}{}{}\n",
-                        current_idx,
-                        JsonProtocolChunkOperator::Assign.get_op(),
-                        source_val.to_string()
-                    ))
+                    Some(ProtocolRow::assign(current_idx, source_val.clone()))
                 }
             },
             Value::String(s) => {
-                let next_accessed = self.next_accessed_idx.get(pointer_expr.as_str()).map(|v| *v);
+                let next_accessed = self.next_accessed_idx.get(pointer_expr.as_str()).copied();
                 if let Some(next_accessed) = next_accessed {
                     if next_accessed >= s.len() {
                         // String processing ended : either go up one level, or terminate if at root
                         // Also cleanup next_accessed_idx
-                        self.next_accessed_idx.remove(pointer_expr.as_str());
-                        if pointer_expr.as_str() == "/" {
-                            return None;
-                        } else {
-                            self.pointer.up();
-                            return self.next();
-                        }
+                        return self.end_node(&pointer_expr, current_idx);
                     }
                     let s = &s[next_accessed..];
                     let mut buf: String;
@@ -180,15 +323,12 @@ impl Iterator for JsonProtocolChunkIter {
                         }
                         &buf
                     };
-                    Some(format!("{}{}{}\n",
-                        current_idx,
-                        JsonProtocolChunkOperator::Append.get_op(),
-                        Value::String(output.to_string()).to_string()
-                    ))
+                    Some(ProtocolRow::append(current_idx, RowValue::Value(Shared::new(Value::String(output.to_string())))))
                 } else {
                     let mut buf: String;
                     // First time looking at this string
                     let output = if self.buf_size >= s.as_bytes().len() {
+                        self.pending_rows.extend(self.close_on_next(&pointer_expr, current_idx));
                         self.next_accessed_idx.insert(pointer_expr, s.as_bytes().len());
                         self.pointer.up();
                         s
@@ -205,26 +345,16 @@ impl Iterator for JsonProtocolChunkIter {
                         }
                         &buf
                     };
-                    Some(format!("{}{}{}\n",
-                        current_idx,
-                        JsonProtocolChunkOperator::Assign.get_op(),
-                        Value::String(output.to_string()).to_string()
-                    ))
+                    Some(ProtocolRow::assign(current_idx, Value::String(output.to_string())))
                 }
             },
             Value::Array(arr) => {
-                let next_accessed = self.next_accessed_idx.get(pointer_expr.as_str()).map(|v| *v);
+                let next_accessed = self.next_accessed_idx.get(pointer_expr.as_str()).copied();
                 if let Some(next_accessed) = next_accessed {
                     if next_accessed >= arr.len() {
                         // Array processing ended : either go up one level, or terminate if at root
                         // Also cleanup next_accessed_idx
-                        self.next_accessed_idx.remove(pointer_expr.as_str());
-                        if pointer_expr.as_str() == "/" {
-                            return None;
-                        } else {
-                            self.pointer.up();
-                            return self.next();
-                        }
+                        return self.end_node(&pointer_expr, current_idx);
                     }
                     // We should proceed here immediately after the else below (on the next iteration), if the map is not empty
                     self.next_accessed_idx.insert(pointer_expr, next_accessed + 1); // Make sure the next one is now updated (for the future iter)
@@ -232,42 +362,32 @@ impl Iterator for JsonProtocolChunkIter {
                     if arr.len() > next_accessed {
                         self.pointer.down(next_accessed.to_string().as_str());
                         self.current_ref_index = Some(self.idx_generator.generate());
-                        return Some(format!("{}{}{}\n",
-                            current_idx,
-                            JsonProtocolChunkOperator::Append.get_op(),
-                            Value::String(format!("{}{}", STREAM_VAR_PREFIX, self.current_ref_index.unwrap()))
-                        ));
+                        let ref_index = self.current_ref_index.unwrap();
+                        return Some(ProtocolRow::append(current_idx, RowValue::Reference(ref_index)));
                     }
                     // Array processing ended
                     self.pointer.up();
                     return self.next();
                 } else {
                     // This is the first time we are looking at this array : parametrize empty
+                    if arr.len() == 0 {
+                        self.pending_rows.extend(self.close_on_next(&pointer_expr, current_idx));
+                    }
                     self.next_accessed_idx.insert(pointer_expr, 0);
                     if arr.len() == 0 {
                         // Empty array : move pointer back
                         self.pointer.up();
                     }
-                    Some(format!("{}{}{}\n",
-                        current_idx,
-                        JsonProtocolChunkOperator::Assign.get_op(),
-                        "[]"
-                    ))
+                    Some(ProtocolRow::assign(current_idx, Value::Array(Vec::new())))
                 }
             },
             Value::Object(map) => {
-                let next_accessed = self.next_accessed_idx.get(pointer_expr.as_str()).map(|v| *v);
+                let next_accessed = self.next_accessed_idx.get(pointer_expr.as_str()).copied();
                 if let Some(next_accessed) = next_accessed {
                     if next_accessed >= map.len() {
                         // Map processing ended : either go up one level, or terminate if at root
                         // Also cleanup next_accessed_idx
-                        self.next_accessed_idx.remove(pointer_expr.as_str());
-                        if pointer_expr.as_str() == "/" {
-                            return None;
-                        } else {
-                            self.pointer.up();
-                            return self.next();
-                        }
+                        return self.end_node(&pointer_expr, current_idx);
                     }
                     // We should proceed here immediately after the else below (on the next iteration), if the map is not empty
                     self.next_accessed_idx.insert(pointer_expr, next_accessed + 1); // Make sure the next one is now updated (for the future iter)
@@ -277,12 +397,8 @@ impl Iterator for JsonProtocolChunkIter {
                             // This element can now be processed
                             self.pointer.down(&key);
                             self.current_ref_index = Some(self.idx_generator.generate());
-                            let value_parametrized = format!("{}{}", STREAM_VAR_PREFIX, self.current_ref_index.unwrap());
-                            return Some(format!("{}{}{}\n",
-                                current_idx,
-                                JsonProtocolChunkOperator::Append.get_op(),
-                                json!({key: value_parametrized}).to_string()
-                            ));
+                            let ref_index = self.current_ref_index.unwrap();
+                            return Some(ProtocolRow::append(current_idx, RowValue::KeyReference(key.clone(), ref_index)));
                         } else {
                             continue;
                         }
@@ -292,29 +408,38 @@ impl Iterator for JsonProtocolChunkIter {
                     return self.next();
                 } else {
                     // This is the first time we are looking at this object : parametrize as empty
+                    if map.len() == 0 {
+                        self.pending_rows.extend(self.close_on_next(&pointer_expr, current_idx));
+                    }
                     self.next_accessed_idx.insert(pointer_expr, 0);
                     if map.len() == 0 {
                         // Empty map : move pointer back
                         self.pointer.up();
                     }
-                    Some(format!("{}{}{}\n",
-                        current_idx,
-                        JsonProtocolChunkOperator::Assign.get_op(),
-                        "{}"
-                    ))
+                    Some(ProtocolRow::assign(current_idx, Value::Object(serde_json::Map::new())))
                 }
             },
         }
     }
 }
 
+impl JsonProtocolChunkStream {
+    /// Frames every row as a Server-Sent Event
+    pub fn sse(self, framer: SseFramer) -> SseStream<Self> {
+        SseStream::new(self, framer)
+    }
+}
+
 impl Stream for JsonProtocolChunkStream {
     type Item = String;
 
-    fn poll_next(mut self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Option<Self::Item>> {
-        // Unfortunately in a single threaded context,
-        // there is no other wait to await asynchonously other than putting the whole thread to sleep
-        thread::sleep(self.sleep_duration);
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if !self.sleep_duration.is_zero() {
+            let sleep_duration = self.sleep_duration;
+            let delay = self.delay.get_or_insert_with(|| Delay::new(sleep_duration));
+            ready!(Pin::new(delay).poll(cx));
+            self.delay = None;
+        }
         Poll::Ready(self.chunker.next())
     }
 }
\ No newline at end of file
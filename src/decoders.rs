@@ -0,0 +1,71 @@
+use crate::json_stream_parser::error::ParseError;
+
+#[cfg(feature = "compression")]
+use content_decoder::{ContentDecoder, ContentEncoding};
+use chunked_transfer_decoder::ChunkedTransferDecoder;
+
+pub mod input_encoding_decoder;
+pub mod chunked_transfer_decoder;
+#[cfg(feature = "compression")]
+pub mod content_decoder;
+
+/// Unwraps a raw HTTP body before it is given to JsonStreamParser :
+/// chunked transfer framing is removed first, then the content is decompressed
+pub struct HttpBodyDecoder {
+    chunked_transfer_decoder: Option<ChunkedTransferDecoder>,
+    #[cfg(feature = "compression")]
+    content_decoder: ContentDecoder,
+    chunked_buffer: Vec<u8>, // Reused across calls, between the two stages
+}
+
+impl HttpBodyDecoder {
+    /// Body with the given transfer coding, and no content coding
+    pub fn new(chunked: bool) -> Self {
+        Self {
+            chunked_transfer_decoder: if chunked { Some(ChunkedTransferDecoder::new()) } else { None },
+            #[cfg(feature = "compression")]
+            content_decoder: ContentDecoder::new(ContentEncoding::Identity),
+            chunked_buffer: Vec::new(),
+        }
+    }
+
+    /// Builder style method to decompress the content
+    #[cfg(feature = "compression")]
+    pub fn with_content_encoding(mut self, content_encoding: ContentEncoding) -> Self {
+        self.content_decoder = ContentDecoder::new(content_encoding);
+        self
+    }
+
+    /// Appends the decoded body contained in bytes to output
+    pub fn decode(&mut self, bytes: &[u8], output: &mut Vec<u8>) -> Result<(), ParseError> {
+        let content = match self.chunked_transfer_decoder.as_mut() {
+            Some(chunked_transfer_decoder) => {
+                self.chunked_buffer.clear();
+                chunked_transfer_decoder.decode(bytes, &mut self.chunked_buffer)?;
+                self.chunked_buffer.as_slice()
+            },
+            None => bytes
+        };
+        #[cfg(feature = "compression")]
+        return self.content_decoder.decode(content, output);
+        #[cfg(not(feature = "compression"))]
+        {
+            output.extend_from_slice(content);
+            return Ok(());
+        }
+    }
+
+    /// Call this method when all data has been sent, to get the remaining data and check that the body is complete
+    pub fn finish(&mut self, output: &mut Vec<u8>) -> Result<(), ParseError> {
+        if let Some(chunked_transfer_decoder) = self.chunked_transfer_decoder.as_ref() {
+            chunked_transfer_decoder.finish()?;
+        }
+        #[cfg(feature = "compression")]
+        return self.content_decoder.finish(output);
+        #[cfg(not(feature = "compression"))]
+        {
+            let _ = output;
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::json_stream_parser::error::ParseError;
+
+/// Incremental decoder for the HTTP/1.1 chunked transfer coding (Transfer-Encoding: chunked)
+/// Framing may be split at any byte across calls to decode()
+/// Chunk extensions and trailer fields are accepted, and ignored
+#[derive(Debug, Clone)]
+pub struct ChunkedTransferDecoder {
+    state: ChunkedState,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ChunkedState {
+    Size { size: usize, has_digits: bool }, // Reading the hexadecimal chunk size
+    Extension { size: usize }, // Ignoring a chunk extension, until the end of the size line
+    SizeLf { size: usize }, // CR of the size line has been read
+    Data { remaining: usize },
+    DataCr, // Expecting the CRLF that ends the chunk data
+    DataLf,
+    Trailer { line_empty: bool }, // After the last chunk : trailer fields, until an empty line
+    TrailerLf { line_empty: bool },
+    Done,
+}
+
+impl ChunkedTransferDecoder {
+    pub fn new() -> Self {
+        Self {
+            state: ChunkedState::Size { size: 0, has_digits: false }
+        }
+    }
+
+    /// True once the last chunk and the trailer have been read. Any byte after that is ignored
+    pub fn is_done(&self) -> bool {
+        self.state == ChunkedState::Done
+    }
+
+    /// Appends the payload contained in bytes to output
+    pub fn decode(&mut self, bytes: &[u8], output: &mut Vec<u8>) -> Result<(), ParseError> {
+        let mut position = 0;
+        while position < bytes.len() {
+            let c = bytes[position];
+            self.state = match (&self.state, c) {
+                (ChunkedState::Data { remaining }, _) => {
+                    // Copy as much of the chunk data as available
+                    let data_len = (*remaining).min(bytes.len() - position);
+                    output.extend_from_slice(&bytes[position..position + data_len]);
+                    position += data_len;
+                    let remaining = remaining - data_len;
+                    if remaining == 0 {
+                        self.state = ChunkedState::DataCr;
+                    } else {
+                        self.state = ChunkedState::Data { remaining };
+                    }
+                    continue;
+                },
+                (ChunkedState::Size { size, .. }, b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F') => {
+                    let digit = (c as char).to_digit(16).unwrap() as usize;
+                    let size = size.checked_mul(16)
+                        .and_then(|size| size.checked_add(digit))
+                        .ok_or_else(|| ParseError::new("Chunk size is too large"))?;
+                    ChunkedState::Size { size, has_digits: true }
+                },
+                (ChunkedState::Size { size, has_digits: true }, b';' | b' ' | b'\t') => ChunkedState::Extension { size: *size },
+                (ChunkedState::Size { size, has_digits: true } | ChunkedState::Extension { size }, b'\r') => ChunkedState::SizeLf { size: *size },
+                (ChunkedState::Extension { size }, _) => ChunkedState::Extension { size: *size },
+                (ChunkedState::SizeLf { size: 0 }, b'\n') => ChunkedState::Trailer { line_empty: true },
+                (ChunkedState::SizeLf { size }, b'\n') => ChunkedState::Data { remaining: *size },
+                (ChunkedState::DataCr, b'\r') => ChunkedState::DataLf,
+                (ChunkedState::DataLf, b'\n') => ChunkedState::Size { size: 0, has_digits: false },
+                (ChunkedState::Trailer { line_empty }, b'\r') => ChunkedState::TrailerLf { line_empty: *line_empty },
+                (ChunkedState::Trailer { .. }, _) => ChunkedState::Trailer { line_empty: false },
+                (ChunkedState::TrailerLf { line_empty: true }, b'\n') => ChunkedState::Done,
+                (ChunkedState::TrailerLf { line_empty: false }, b'\n') => ChunkedState::Trailer { line_empty: true },
+                (ChunkedState::Done, _) => return Ok(()),
+                (state, _) => {
+                    return Err(ParseError::new(format!("Invalid chunked transfer encoding : unexpected byte {} in state {:?}", crate::byte_to_char(&c), state)))
+                },
+            };
+            position += 1;
+        }
+        Ok(())
+    }
+
+    /// Call this method when all data has been sent, to check that the body has not been cut
+    pub fn finish(&self) -> Result<(), ParseError> {
+        if self.is_done() {
+            Ok(())
+        } else {
+            Err(ParseError::new("Chunked transfer encoding ended before the last chunk"))
+        }
+    }
+}
+
+impl Default for ChunkedTransferDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
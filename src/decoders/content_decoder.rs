@@ -0,0 +1,126 @@
+use std::io::Write;
+
+use flate2::{write::GzDecoder, Decompress, FlushDecompress, Status};
+
+use crate::json_stream_parser::error::ParseError;
+
+/// Content codings of an HTTP body (Content-Encoding header)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentEncoding {
+    #[default]
+    Identity,
+    Gzip,
+    Deflate, // zlib wrapped as per the HTTP spec, raw deflate streams sent by some servers are accepted too
+}
+
+impl ContentEncoding {
+    /// Parses the value of a Content-Encoding header. Returns None for unsupported codings
+    pub fn from_header(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "identity" => Some(ContentEncoding::Identity),
+            "gzip" | "x-gzip" => Some(ContentEncoding::Gzip),
+            "deflate" => Some(ContentEncoding::Deflate),
+            _ => None
+        }
+    }
+}
+
+/// Incremental decompression of an HTTP body
+/// Compressed data may be split at any byte across calls to decode()
+pub struct ContentDecoder {
+    encoding: ContentEncoding,
+    inflater: Option<Inflater>, // Created with the first bytes, as the deflate flavor is detected from them
+    pending: Vec<u8>, // First byte of a deflate body, until the zlib header can be checked
+}
+
+enum Inflater {
+    Gzip(GzDecoder<Vec<u8>>), // Checks the gzip trailer, detecting truncated bodies
+    Deflate { decompress: Decompress, stream_end: bool }, // zlib or raw deflate
+}
+
+const INFLATE_BUFFER_SIZE: usize = 8192;
+
+impl ContentDecoder {
+    pub fn new(encoding: ContentEncoding) -> Self {
+        Self {
+            encoding,
+            inflater: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Appends the decompressed data contained in bytes to output
+    pub fn decode(&mut self, bytes: &[u8], output: &mut Vec<u8>) -> Result<(), ParseError> {
+        if self.inflater.is_none() {
+            match self.encoding {
+                ContentEncoding::Identity => {
+                    output.extend_from_slice(bytes);
+                    return Ok(());
+                },
+                ContentEncoding::Gzip => {
+                    self.inflater = Some(Inflater::Gzip(GzDecoder::new(Vec::new())));
+                },
+                ContentEncoding::Deflate => {
+                    self.pending.extend_from_slice(bytes);
+                    if self.pending.len() < 2 {
+                        return Ok(());
+                    }
+                    // zlib header : compression method 8, and header checksum
+                    let (cmf, flg) = (self.pending[0], self.pending[1]);
+                    let zlib_header = cmf & 0x0F == 8 && (((cmf as u16) << 8) | flg as u16).is_multiple_of(31);
+                    self.inflater = Some(Inflater::Deflate { decompress: Decompress::new(zlib_header), stream_end: false });
+                    let pending = std::mem::take(&mut self.pending);
+                    return self.inflate(&pending, output);
+                },
+            }
+        }
+        self.inflate(bytes, output)
+    }
+
+    /// Call this method when all data has been sent, to get the remaining data and check that the body is complete
+    pub fn finish(&mut self, output: &mut Vec<u8>) -> Result<(), ParseError> {
+        if !self.pending.is_empty() {
+            // Too short to detect the flavor : it cannot be a valid zlib stream anyway
+            self.inflater = Some(Inflater::Deflate { decompress: Decompress::new(false), stream_end: false });
+            let pending = std::mem::take(&mut self.pending);
+            self.inflate(&pending, output)?;
+        }
+        match self.inflater.as_mut() {
+            Some(Inflater::Gzip(decoder)) => {
+                decoder.try_finish().map_err(|err| ParseError::new(format!("Invalid compressed data : {err}")))?;
+                output.append(decoder.get_mut());
+                Ok(())
+            },
+            Some(Inflater::Deflate { stream_end: false, .. }) => Err(ParseError::new("Compressed data ended before the end of the stream")),
+            _ => Ok(()),
+        }
+    }
+
+    fn inflate(&mut self, bytes: &[u8], output: &mut Vec<u8>) -> Result<(), ParseError> {
+        match self.inflater.as_mut().unwrap() {
+            Inflater::Gzip(decoder) => {
+                decoder.write_all(bytes).map_err(|err| ParseError::new(format!("Invalid compressed data : {err}")))?;
+                output.append(decoder.get_mut());
+            },
+            Inflater::Deflate { decompress, stream_end } => {
+                let mut input = bytes;
+                let mut buffer = [0u8; INFLATE_BUFFER_SIZE];
+                // Loop until the input is consumed, and the output buffer is not filled anymore
+                while !*stream_end {
+                    let (total_in, total_out) = (decompress.total_in(), decompress.total_out());
+                    let status = decompress.decompress(input, &mut buffer, FlushDecompress::None)
+                        .map_err(|err| ParseError::new(format!("Invalid compressed data : {err}")))?;
+                    let consumed = (decompress.total_in() - total_in) as usize;
+                    let produced = (decompress.total_out() - total_out) as usize;
+                    output.extend_from_slice(&buffer[..produced]);
+                    input = &input[consumed..];
+                    *stream_end = status == Status::StreamEnd; // Any data after the end is ignored
+                    if (input.is_empty() && produced < buffer.len()) || (consumed == 0 && produced == 0) {
+                        break;
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+/// Encoding of the bytes given to the parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputEncoding {
+    #[default]
+    Utf8, // A leading BOM is stripped, bytes are passed as is otherwise
+    Utf16Le,
+    Utf16Be,
+    Latin1, // ISO-8859-1, never detected automatically
+    Auto, // Detected from the BOM, or from the zero bytes of UTF-16 encoded ASCII. Falls back to UTF-8
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Incremental decoder turning the input bytes into UTF-8 before they reach the parser
+/// Characters split across chunks are kept until the next chunk completes them
+#[derive(Debug, Clone)]
+pub struct InputEncodingDecoder {
+    encoding: InputEncoding, // Becomes the detected encoding once the start of the input is known
+    start_detected: bool, // Whether the BOM has been checked
+    pending: Vec<u8>, // Bytes kept until the start is detected, or the odd byte of a UTF-16 code unit
+    high_surrogate: Option<u16>, // First half of a UTF-16 surrogate pair
+}
+
+impl InputEncodingDecoder {
+    pub fn new(encoding: InputEncoding) -> Self {
+        Self {
+            encoding,
+            start_detected: false,
+            pending: Vec::new(),
+            high_surrogate: None,
+        }
+    }
+
+    /// Encoding being decoded. For InputEncoding::Auto, this is the detected one once enough bytes have been read
+    pub fn encoding(&self) -> InputEncoding {
+        self.encoding
+    }
+
+    /// Whether the next bytes are given back as they are, as they are already UTF-8 and past any BOM
+    #[inline]
+    pub fn is_passthrough(&self) -> bool {
+        self.start_detected && self.encoding == InputEncoding::Utf8
+    }
+
+    /// Decodes a chunk of bytes into UTF-8
+    /// Returns the decoded bytes, which are either written into output, or the input itself when no conversion is needed
+    pub fn decode<'a>(&mut self, bytes: &'a [u8], output: &'a mut Vec<u8>) -> &'a [u8] {
+        if self.is_passthrough() {
+            // Nothing to do : avoid copying
+            return bytes;
+        }
+        let mut bytes = bytes;
+        if !self.start_detected {
+            if self.pending.is_empty() {
+                // The start fits in this chunk : decode it right away rather than copying it into the pending bytes
+                if let Some(bom_len) = self.detect_bom(bytes, false) {
+                    self.start_detected = true;
+                    if self.is_passthrough() {
+                        return &bytes[bom_len..];
+                    }
+                    self.decode_bytes(&bytes[bom_len..], output);
+                    return output;
+                }
+            }
+            self.pending.extend_from_slice(bytes);
+            if !self.detect_start(false) {
+                return &output[..0];
+            }
+            let pending = std::mem::take(&mut self.pending);
+            self.decode_bytes(&pending, output);
+            return output;
+        }
+        if self.encoding == InputEncoding::Utf16Le || self.encoding == InputEncoding::Utf16Be {
+            if let Some(odd_byte) = self.pending.pop() {
+                // Complete the code unit split across chunks
+                if let Some((first, rest)) = bytes.split_first() {
+                    self.decode_bytes(&[odd_byte, *first], output);
+                    bytes = rest;
+                } else {
+                    self.pending.push(odd_byte);
+                }
+            }
+        }
+        self.decode_bytes(bytes, output);
+        output
+    }
+
+    /// Call when all the input has been given, to decode the lingering bytes
+    /// Incomplete UTF-16 characters are replaced with U+FFFD
+    pub fn finish(&mut self, output: &mut Vec<u8>) {
+        if !self.start_detected {
+            self.detect_start(true);
+            let pending = std::mem::take(&mut self.pending);
+            self.decode_bytes(&pending, output);
+        }
+        if !self.pending.is_empty() || self.high_surrogate.is_some() {
+            self.pending.clear();
+            self.high_surrogate = None;
+            push_char(char::REPLACEMENT_CHARACTER, output);
+        }
+    }
+
+    /// Checks the pending bytes for a BOM, which is removed, and detects the encoding in Auto mode
+    /// Returns false if more bytes are needed, unless at the end of the input
+    fn detect_start(&mut self, end_of_input: bool) -> bool {
+        let pending = std::mem::take(&mut self.pending);
+        let bom_len = self.detect_bom(&pending, end_of_input);
+        self.pending = pending;
+        let Some(bom_len) = bom_len else {
+            return false;
+        };
+        self.pending.drain(..bom_len);
+        self.start_detected = true;
+        true
+    }
+
+    /// Looks for a BOM at the start of the input, and detects the encoding in Auto mode
+    /// Returns the length of the BOM, or None if more bytes are needed, unless at the end of the input
+    fn detect_bom(&mut self, bytes: &[u8], end_of_input: bool) -> Option<usize> {
+        let bom_len = match self.encoding {
+            InputEncoding::Utf8 => {
+                // Only wait for more bytes when they might be a BOM
+                if !end_of_input && bytes.len() < UTF8_BOM.len() && UTF8_BOM.starts_with(bytes) {
+                    return None;
+                }
+                if bytes.starts_with(UTF8_BOM) { UTF8_BOM.len() } else { 0 }
+            },
+            InputEncoding::Utf16Le | InputEncoding::Utf16Be => {
+                if !end_of_input && bytes.len() < 2 {
+                    return None;
+                }
+                let bom = if self.encoding == InputEncoding::Utf16Le { UTF16LE_BOM } else { UTF16BE_BOM };
+                if bytes.starts_with(bom) { bom.len() } else { 0 }
+            },
+            InputEncoding::Latin1 => 0,
+            InputEncoding::Auto => {
+                if !end_of_input && (bytes.len() < 2 || (bytes.len() < UTF8_BOM.len() && UTF8_BOM.starts_with(bytes))) {
+                    return None;
+                }
+                let (encoding, bom_len) = match bytes {
+                    [0xEF, 0xBB, 0xBF, ..] => (InputEncoding::Utf8, UTF8_BOM.len()),
+                    [0xFF, 0xFE, ..] => (InputEncoding::Utf16Le, UTF16LE_BOM.len()),
+                    [0xFE, 0xFF, ..] => (InputEncoding::Utf16Be, UTF16BE_BOM.len()),
+                    // JSON starts with an ASCII character : in UTF-16, one of its two bytes is zero
+                    [first, 0x00, ..] if *first != 0 => (InputEncoding::Utf16Le, 0),
+                    [0x00, second, ..] if *second != 0 => (InputEncoding::Utf16Be, 0),
+                    _ => (InputEncoding::Utf8, 0)
+                };
+                self.encoding = encoding;
+                bom_len
+            },
+        };
+        Some(bom_len)
+    }
+
+    fn decode_bytes(&mut self, bytes: &[u8], output: &mut Vec<u8>) {
+        match self.encoding {
+            InputEncoding::Utf8 | InputEncoding::Auto => output.extend_from_slice(bytes),
+            InputEncoding::Latin1 => {
+                for byte in bytes {
+                    push_char(*byte as char, output); // Latin-1 maps directly to the first 256 code points
+                }
+            },
+            InputEncoding::Utf16Le | InputEncoding::Utf16Be => {
+                let mut code_units = bytes.chunks_exact(2);
+                for code_unit in &mut code_units {
+                    let code_unit = if self.encoding == InputEncoding::Utf16Le {
+                        u16::from_le_bytes([code_unit[0], code_unit[1]])
+                    } else {
+                        u16::from_be_bytes([code_unit[0], code_unit[1]])
+                    };
+                    self.decode_code_unit(code_unit, output);
+                }
+                self.pending.extend_from_slice(code_units.remainder());
+            },
+        }
+    }
+
+    fn decode_code_unit(&mut self, code_unit: u16, output: &mut Vec<u8>) {
+        match (self.high_surrogate.take(), code_unit) {
+            (Some(high), 0xDC00..=0xDFFF) => {
+                let code_point = 0x10000 + (((high as u32) - 0xD800) << 10) + ((code_unit as u32) - 0xDC00);
+                push_char(char::from_u32(code_point).unwrap_or(char::REPLACEMENT_CHARACTER), output);
+            },
+            (high, _) => {
+                if high.is_some() {
+                    // Unpaired high surrogate
+                    push_char(char::REPLACEMENT_CHARACTER, output);
+                }
+                match code_unit {
+                    0xD800..=0xDBFF => self.high_surrogate = Some(code_unit),
+                    0xDC00..=0xDFFF => push_char(char::REPLACEMENT_CHARACTER, output), // Unpaired low surrogate
+                    _ => push_char(char::from_u32(code_unit as u32).unwrap(), output),
+                }
+            },
+        }
+    }
+}
+
+#[inline]
+fn push_char(c: char, output: &mut Vec<u8>) {
+    let mut utf8_buf = [0u8; 4];
+    output.extend_from_slice(c.encode_utf8(&mut utf8_buf).as_bytes());
+}
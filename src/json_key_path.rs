@@ -8,14 +8,17 @@
 /// "parent.*"  # Wildcard for any key of 'parent' which is object or array
 /// "*"         # Wildcard for any key of root which is an object or array
 /// "*.*"       # Wildcard for any second level base type (non object or array) within nested object/array
+#[derive(Clone)]
 pub struct JsonKeyPath {
-    current_key: String
+    current_key: String,
+    segment_starts: Vec<usize>, // Position of each key within current_key, as keys may contain dots
 }
 
 impl JsonKeyPath {
     pub fn new() -> Self {
         Self {
-            current_key: String::new()
+            current_key: String::new(),
+            segment_starts: Vec::new()
         }
     }
 
@@ -24,35 +27,67 @@ impl JsonKeyPath {
     }
 
     /// Moving down an object with key
+    /// Returns false for an empty key, which is not moved down into
     pub fn move_down_object_or_array(&mut self, key: &str) -> bool {
         if key.len() == 0 {
             return false;
         }
-        if self.current_key.len() > 0 {
+        self.move_down(key);
+        true
+    }
+
+    /// Moving down with any key, including an empty one, so that the parser's move_up stays balanced
+    pub(crate) fn move_down(&mut self, key: &str) {
+        if !self.segment_starts.is_empty() {
             self.current_key.push('.');
         }
+        self.segment_starts.push(self.current_key.len());
         self.current_key.push_str(key);
-        true
     }
 
     /// Moving up one level
     /// Returns false if trying to move up at root, or any malformed key expression
     pub fn move_up(&mut self) -> bool {
-        if self.current_key.len() == 0 {
-            return false;
+        match self.segment_starts.pop() {
+            Some(segment_start) => {
+                // Also remove the dot before the key, unless moving up to root
+                self.current_key.truncate(segment_start.saturating_sub(1));
+                true
+            },
+            None => false
         }
-        let mut last_dot_position = 0;
-        
-        for (indice, char_value) in self.current_key.char_indices().rev() {
-            // If 0 is reached, we moved up to root
-            if char_value == '.' {
-                last_dot_position = indice;
-                break;
+    }
+
+    /// Nesting level of the current element, root being 0
+    pub fn depth(&self) -> usize {
+        self.segment_starts.len()
+    }
+
+    /// Keys from the root to the current element, without any escaping
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        let ends = self.segment_starts.iter().skip(1).map(|start| start - 1).chain(std::iter::once(self.current_key.len()));
+        self.segment_starts.iter().zip(ends).map(|(start, end)| &self.current_key[*start..end])
+    }
+
+    /// Key of the current element within its parent, None at root
+    pub fn last_segment(&self) -> Option<&str> {
+        self.segment_starts.last().map(|start| &self.current_key[*start..])
+    }
+
+    /// Path of the current element as a JSON pointer (RFC 6901), root being an empty string
+    pub fn to_json_pointer(&self) -> String {
+        let mut pointer = String::with_capacity(self.current_key.len() + 1);
+        for segment in self.segments() {
+            pointer.push('/');
+            for c in segment.chars() {
+                match c {
+                    '~' => pointer.push_str("~0"),
+                    '/' => pointer.push_str("~1"),
+                    c => pointer.push(c),
+                }
             }
         }
-        
-        self.current_key.drain(last_dot_position..);
-        true
+        pointer
     }
 
     /// Matches an expression which may include a wildcard. Refer to doc of JsonKeyPath for more details
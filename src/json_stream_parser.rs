@@ -1,14 +1,14 @@
 use derivative::Derivative;
-use error::ParseError;
+use error::{FinishError, ParseError, ParseWarning};
 use parser_options::ParserOptions;
-use parser_output::ParserOutputTrait;
+use parser_output::{parser_output_none::ParserOutputNone, ParserOutputTrait};
 use partial_json_mapper::PartialJsonMapper;
 use serde_json::Value;
 use status::{Status, StatusTrait};
 
-use crate::{byte_to_char, ref_index_generator::RefIndexGenerator};
+use crate::{byte_to_char, decoders::input_encoding_decoder::InputEncodingDecoder, ref_index_generator::RefIndexGenerator, stream_protocol::protocol_row::ProtocolRow, Shared};
 
-pub(crate) mod error;
+pub mod error;
 //pub(crate) mod json_tree;
 pub(crate) mod partial_json_mapper;
 pub(crate) mod status;
@@ -16,38 +16,45 @@ pub(crate) mod status;
 pub mod parser_options;
 pub mod parser_output;
 
-use std::rc::Rc;
-#[cfg( feature = "async" )] use std::{io::Error, task::Poll};
-#[cfg( feature = "async" )] use futures::AsyncWrite;
-#[cfg( feature = "async" )] use pin_project::pin_project;
-#[cfg( feature = "async" )]
-#[pin_project]
-pub struct JsonStreamParser<W, F, O> {
-    mapper: PartialJsonMapper<F, O>,
-    current_status: Status,
-    char_pos: usize,
-    #[pin]
-    writer: W,
-}
-
-#[cfg(not(feature = "async" ))]
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct JsonStreamParser<F, O> {
     #[derivative(Debug="ignore")]
     mapper: PartialJsonMapper<F, O>,
+    decoder: InputEncodingDecoder, // Converts the input to UTF-8 before it reaches the mapper
+    decode_buffer: Vec<u8>, // Reused across calls, for inputs that need converting
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+/// Convenience type for event handlers, when handlers of different kinds need to be attached to the same parser
+/// With the "sync" feature, handlers must be Send + Sync so that the parser can be moved across threads
+#[cfg(not(feature = "sync"))]
+pub type BoxedEventHandler = Box<dyn Fn(Option<Shared<Value>>)>;
+#[cfg(feature = "sync")]
+pub type BoxedEventHandler = Box<dyn Fn(Option<Shared<Value>>) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ParserEvent {
     OnElementBegin,
     OnElementEnd
 }
 
-#[cfg( not(feature = "async") )]
+/// Parses an incomplete JSON document at once, returning it as if it ended there (see current_partial_value)
+/// Useful to render partial LLM outputs. An empty input gives null
+pub fn parse_partial(input: &str) -> Result<Value, ParseError> {
+    let mut json_stream_parser: JsonStreamParser<BoxedEventHandler, _> = JsonStreamParser::new(
+        RefIndexGenerator::new(),
+        0,
+        true,
+        ParserOptions::default(),
+        ParserOutputNone::new()
+    );
+    json_stream_parser.feed(input.as_bytes())?;
+    Ok(json_stream_parser.current_partial_value().unwrap_or(Value::Null))
+}
+
 impl<F, O> JsonStreamParser<F, O>
 where
-    F: Fn(Option<Rc<Value>>) -> (),
+    F: Fn(Option<Shared<Value>>) -> (),
     O: ParserOutputTrait
 {
     pub fn new(
@@ -58,6 +65,8 @@ where
         parser_output: O
     ) -> JsonStreamParser<F, O> {
         JsonStreamParser {
+            decoder: InputEncodingDecoder::new(parser_options.input_encoding),
+            decode_buffer: Vec::new(),
             mapper: PartialJsonMapper::new(
                 ref_index_generator,
                 current_node_index,
@@ -70,24 +79,87 @@ where
 
     #[inline]
     pub fn add_char(&mut self, c: &u8) -> Result<Option<String>, ParseError> {
-        match self.mapper.add_char(c) {
-            Ok(Some(output)) => {
-                Ok(Some(output.into()))
-            }
-            Ok(None) => {
-                // Not ready to write the line yet, do nothing
-                Ok(None)
-            }
-            Err(err) => {
+        let mut output = String::new();
+        self.add_char_into(c, &mut output)?;
+        Ok(if !output.is_empty() { Some(output) } else { None })
+    }
+
+    /// Same as add_char, but the rows are appended to the given output instead of being returned
+    /// Allows reusing the same buffer across calls, without allocating for every row
+    #[inline]
+    pub fn add_char_into(&mut self, c: &u8, output: &mut String) -> Result<(), ParseError> {
+        if self.decoder.is_passthrough() {
+            // Most common case, which does not need to go through the decode buffer
+            return self.mapper.add_char(c, output).map_err(|err| {
                 log::error!("JSON parse error at character '{}' : {}", byte_to_char(c), err.msg);
-                Err(err)
-            },
+                err
+            });
         }
+        let mut decode_buffer = std::mem::take(&mut self.decode_buffer);
+        decode_buffer.clear();
+        // Depending on the input encoding, a byte may give zero or several UTF-8 bytes
+        let result = self.decoder.decode(std::slice::from_ref(c), &mut decode_buffer)
+            .iter()
+            .try_for_each(|decoded_c| {
+                self.mapper.add_char(decoded_c, output).map_err(|err| {
+                    log::error!("JSON parse error at character '{}' : {}", byte_to_char(decoded_c), err.msg);
+                    err
+                })
+            });
+        self.decode_buffer = decode_buffer;
+        result
+    }
+
+    /// Processes a whole chunk of bytes at once, returning all the rows written meanwhile
+    /// The result is the same as calling add_char for each byte, but string contents are scanned in bulk
+    /// Only string contents, keys included, are sped up : numbers, literals and punctuation are still processed byte by byte
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<Option<String>, ParseError> {
+        // Rows hold the input along with their prefixes : sizing the output after the input spares most reallocations
+        let mut output = String::with_capacity(bytes.len());
+        self.feed_into(bytes, &mut output)?;
+        Ok(if !output.is_empty() { Some(output) } else { None })
+    }
+
+    /// Same as feed, but the rows are appended to the given output instead of being returned
+    /// Rows already written before an error are kept in the output
+    pub fn feed_into(&mut self, bytes: &[u8], output: &mut String) -> Result<(), ParseError> {
+        let mut decode_buffer = std::mem::take(&mut self.decode_buffer);
+        decode_buffer.clear();
+        let bytes = self.decoder.decode(bytes, &mut decode_buffer);
+        let result = self.mapper.feed(bytes, output).map_err(|(position, err)| {
+            log::error!("JSON parse error at character '{}' : {}", byte_to_char(&bytes[position]), err.msg);
+            err
+        });
+        self.decode_buffer = decode_buffer;
+        result
     }
 
     #[inline]
     pub fn flush(&mut self) -> Option<String> {
-        self.mapper.flush()
+        let mut output = String::new();
+        self.flush_into(&mut output);
+        if !output.is_empty() { Some(output) } else { None }
+    }
+
+    /// Same as flush, but the row is appended to the given output instead of being returned
+    #[inline]
+    pub fn flush_into(&mut self, output: &mut String) {
+        self.mapper.flush(output);
+    }
+
+    /// Checks the flush policy without adding any byte, returning the row written if the string in progress had to be flushed
+    /// To be called periodically with FlushPolicy::Interval, so that buffered bytes are written even while the input is stalled
+    #[inline]
+    pub fn poll_flush(&mut self) -> Option<String> {
+        let mut output = String::new();
+        self.poll_flush_into(&mut output);
+        if !output.is_empty() { Some(output) } else { None }
+    }
+
+    /// Same as poll_flush, but the row is appended to the given output instead of being returned
+    #[inline]
+    pub fn poll_flush_into(&mut self, output: &mut String) {
+        self.mapper.poll_flush(output);
     }
 
     /// Attach a function to be executed when an event occurs at a given element
@@ -103,6 +175,9 @@ where
     /// If this function is used, then by default the parser will not output a JSON unless it matches any element of the filter
     /// Element is a simple string path to a JSON key. Ex: "parent.child.grandchildren.0.name"
     pub fn set_options(&mut self, parser_options: ParserOptions) {
+        if parser_options.input_encoding != self.mapper.get_options().input_encoding {
+            self.decoder = InputEncodingDecoder::new(parser_options.input_encoding);
+        }
         self.mapper.set_options(parser_options);
     }
 
@@ -120,118 +195,87 @@ where
         self.mapper.take_buffered_data()
     }
 
-    /// This method needs to be called upon ending the parsing to ensure properly handling the lingering state
-    /// One such case is when the json is a single number - because of the absence of a character indicating the end of the number,
-    /// the parser cannot properly buffer it unless finish() is called
-    pub fn finish(&mut self) {
-        self.mapper.finish();
+    /// Output the parser writes into, for outputs keeping state (ex: ProtocolRowOutput::take_rows)
+    pub fn parser_output(&self) -> &O {
+        self.mapper.parser_output()
     }
-}
 
-#[cfg(feature = "async")]
-impl<W, F> JsonStreamParser<W, F, O>
-where
-    W: AsyncWrite,
-    F: Fn(Option<Rc<Value>>) -> (),
-    O: ParserOutputTrait
-{
-    pub fn new(
-        ref_index_generator: RefIndexGenerator,
-        writer: W,
-        current_node_index: usize,
-        parser_output: O
-        
-    ) -> JsonStreamParser<W, F, O> {
-        JsonStreamParser {
-            mapper: PartialJsonMapper::new(ref_index_generator, current_node_index),
-            current_status: Status::new(),
-            char_pos: 0,
-            writer,
-            parser_output
-        }
+    pub fn parser_output_mut(&mut self) -> &mut O {
+        self.mapper.parser_output_mut()
     }
 
-    #[inline]
-    pub fn add_char(&mut self, c: &u8) -> Result<Option<String>, ParseError> {
-        match self.mapper.add_char(c) {
-            Ok(Some(output)) => {
-                Ok(Some(output.into()))
-            }
-            Ok(None) => {
-                // Not ready to write the line yet, do nothing
-                Ok(None)
-            }
-            Err(err) => {
-                log::error!("JSON parse error at character '{}' : {}", byte_to_char(c), err.msg);
-                Err(err)
-            },
-        }
+    /// Malformed input recovered from so far, when the recovery mode is enabled (see ParserOptions::with_recovery)
+    pub fn warnings(&self) -> &[ParseWarning] {
+        self.mapper.warnings()
     }
 
-    #[inline]
-    pub fn flush(&mut self) -> Option<String> {
-        self.mapper.flush()
+    /// Moves out the warnings recorded so far
+    pub fn take_warnings(&mut self) -> Vec<ParseWarning> {
+        self.mapper.take_warnings()
     }
 
-    /// Attach a function to be executed when an event occurs at a given element
-    /// Element is a simple string path to a JSON key. Ex: "parent.child.grandchildren.0.name"
-    /// Json key path uses dot notation to separate levels. Array index can be replaced with wildcard (*) to match every element
-    /// The parameter to the event function is set to the value of the element when the event is OnElementEnd,
-    /// but only when the element is not an Array or an Object - for performance reasons (set to None in all other cases)
-    pub fn add_event_handler(&mut self, event: ParserEvent, element: String, func: F) {
-        self.mapper.add_event_handler(event, element, func);
+    /// Returns the document as it is right now, with every open object and array closed
+    /// Unlike get_buffered_data, the value in progress is included : strings with what has been received so far,
+    /// numbers without their trailing incomplete characters (ex: "12." gives 12), and partial literals as null
+    /// Keys still being parsed, or without any value yet, are left out
+    /// Returns None when buffering is disabled, or when nothing has been parsed yet
+    pub fn current_partial_value(&self) -> Option<Value> {
+        self.mapper.current_partial_value()
     }
 
-    /// Builder style version of add_event_handler method
-    pub fn with_event_handler(mut self, event: ParserEvent, element: String, func: F) -> Self {
-        self.mapper.add_event_handler(event, element, func);
-        self
+    /// Rows bringing a client joining mid-stream to the current state of the document, under the same indices as the live rows
+    /// Once applied, the client can follow the rows written from now on (encode them with TextRowEncoder for the text form)
+    /// The header, if any, is not included (see StreamProtocolOutput::protocol_header)
+    /// Returns None when buffering is disabled. Buffer filters should keep everything the output filters keep
+    pub fn resync_rows(&self) -> Option<Vec<ProtocolRow>> {
+        self.mapper.resync_rows()
     }
-}
 
-#[cfg( feature = "async" )]
-impl<W, F> AsyncWrite for JsonStreamParser<W, F, O>
-where
-    W: AsyncWrite,
-    F: Fn(Option<Rc<Value>>) -> (),
-    O: ParserOutputTrait
-{
-    fn poll_write(
-        self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-        buf: &[u8],
-    ) -> std::task::Poll<std::io::Result<usize>> {
-        let this = self.project();
-        let mut final_output: String = String::new();
-        for c in buf {
-            match this.mapper.add_char(c) {
-                Ok(Some(output)) => {
-                    final_output.push_str(&output.into());
-                }
-                Ok(None) => {
-                    // Not ready to write the line yet, do nothing
-                }
-                Err(err) => {
-                    log::error!("JSON parse error at character '{}' : {}", byte_to_char(c), err.msg);
-                    return Poll::Ready(Err(Error::other(format!("JSON Parse error : {}", err.msg))));
-                },
+    /// This method needs to be called upon ending the parsing to ensure properly handling the lingering state
+    /// One such case is when the json is a single number - because of the absence of a character indicating the end of the number,
+    /// the parser cannot properly buffer it unless finish_into() is called
+    /// Returns an error describing the incomplete elements when the document has been cut before its end
+    /// (unterminated strings, open objects and arrays, keys without values), allowing to detect an upstream disconnect
+    /// The last rows are appended to the given output : a root number, which is only complete now,
+    /// and the end of the stream or the error (see StreamProtocolOutput::with_end_rows)
+    pub fn finish_into(&mut self, output: &mut String) -> Result<(), FinishError> {
+        // Lingering input bytes, for ex. a single byte input while detecting the encoding
+        let mut decode_buffer = Vec::new();
+        self.decoder.finish(&mut decode_buffer);
+        if !decode_buffer.is_empty() {
+            if let Err((position, err)) = self.mapper.feed(&decode_buffer, output) {
+                log::error!("JSON parse error at character '{}' : {}", byte_to_char(&decode_buffer[position]), err.msg);
             }
         }
-        
-        this.writer.poll_write(cx, final_output.as_bytes())
+        self.mapper.finish(output).map_err(|err| {
+            log::error!("{}", err);
+            err
+        })
     }
 
-    fn poll_flush(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<std::io::Result<()>> {
-        let this = self.project();
-        if let Some(out) = this.mapper.flush() {
-            this.writer.poll_write(cx, out.as_bytes()).map(|result| result.map(|_| (())))
-        } else {
-            Poll::Ready(Ok(()))
+    /// Makes an independent copy of the parser, to feed speculative bytes into without affecting self
+    /// The fork uses its own copy of the index generator, so that indices it generates are not consumed from the shared one
+    /// While the fork is alive, it must be the only one generating indices (see RefIndexGenerator::fork)
+    /// Event handlers stay attached to the original parser : events of the fork are recorded, and handled on commit()
+    /// The fork can either be dropped to discard the speculative bytes, or passed to commit()
+    pub fn fork(&self) -> Self
+    where
+        O: Clone
+    {
+        JsonStreamParser {
+            mapper: self.mapper.fork(),
+            decoder: self.decoder.clone(),
+            decode_buffer: Vec::new(),
         }
     }
 
-    fn poll_close(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<std::io::Result<()>> {
-        let this = self.project();
-        this.writer.poll_close(cx)
+    /// Replaces the parsing state (including buffered data) with the one of a fork made by fork()
+    /// The shared index generator is advanced so that indices used by the fork are never generated again,
+    /// and the event handlers are called for the events of the fork
+    /// Fails without any change if indices have been generated outside the fork as well, as rows of both would then clash
+    pub fn commit(&mut self, forked: Self) -> Result<(), ParseError> {
+        self.mapper.commit(forked.mapper)?;
+        self.decoder = forked.decoder;
+        Ok(())
     }
 }
\ No newline at end of file
@@ -17,6 +17,13 @@ impl ToString for ParseError {
     }
 }
 
+/// Malformed input that the parser has recovered from, when the recovery mode is enabled
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseWarning {
+    pub position: usize, // Byte offset in the input
+    pub msg: String
+}
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct LogicalError {
@@ -68,4 +75,48 @@ impl From<std::num::ParseIntError> for ParseError {
             msg: "Int parse error".to_string()
         }
     }
-}
\ No newline at end of file
+}
+/// Error returned by finish_into() when the document has been cut before its end
+/// Lists every element left incomplete, from the innermost one up to the root
+#[derive(Debug, Clone, PartialEq)]
+pub struct FinishError {
+    pub truncations: Vec<Truncation>
+}
+
+/// An element left incomplete at the end of the document
+/// Key paths use the dot notation of JsonKeyPath, root being an empty string
+#[derive(Debug, Clone, PartialEq)]
+pub enum Truncation {
+    EmptyDocument, // Not a single value has started
+    UnterminatedString { key_path: String, object_key: bool }, // For an object key, the key path is the one of the object
+    UnterminatedLiteral { key_path: String }, // Partial null, true or false
+    InvalidNumber { key_path: String, msg: String },
+    DanglingKey { key_path: String, key: String }, // Object key without a value. The key path is the one of the object
+    OpenObject { key_path: String },
+    OpenArray { key_path: String },
+}
+
+impl std::fmt::Display for FinishError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FinishError: document is incomplete")?;
+        for truncation in &self.truncations {
+            write!(f, "\n - {}", truncation)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Truncation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Truncation::EmptyDocument => write!(f, "empty document"),
+            Truncation::UnterminatedString { key_path, object_key: true } => write!(f, "unterminated key in object at '{}'", key_path),
+            Truncation::UnterminatedString { key_path, object_key: false } => write!(f, "unterminated string at '{}'", key_path),
+            Truncation::UnterminatedLiteral { key_path } => write!(f, "unterminated literal at '{}'", key_path),
+            Truncation::InvalidNumber { key_path, msg } => write!(f, "invalid number at '{}' : {}", key_path, msg),
+            Truncation::DanglingKey { key_path, key } => write!(f, "key '{}' without value in object at '{}'", key, key_path),
+            Truncation::OpenObject { key_path } => write!(f, "unclosed object at '{}'", key_path),
+            Truncation::OpenArray { key_path } => write!(f, "unclosed array at '{}'", key_path),
+        }
+    }
+}
@@ -1,9 +1,17 @@
-#[derive(Default)]
+use std::{fmt::Debug, time::{Duration, Instant}};
+
+use crate::{decoders::input_encoding_decoder::InputEncoding, Shared};
+
+#[derive(Default, Clone)]
 pub struct ParserOptions {
     pub filter: ParserOptionsFilter,
+    pub grapheme_safe_flush: bool, // When set, flush() keeps back the trailing grapheme cluster of a string until more bytes arrive or the string ends
+    pub flush_policy: FlushPolicy, // When to flush automatically while adding bytes
+    pub recovery: bool, // When set, malformed input is recovered from instead of returning a ParseError (see with_recovery)
+    pub input_encoding: InputEncoding, // Encoding of the input bytes, converted to UTF-8 before parsing
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct ParserOptionsFilter {
     pub output_whitelist: Option<Vec<String>>, // An optional list of json paths to apply for a whitelist of the output data
     pub buffer_whitelist: Option<Vec<String>>, // An optional list of json paths to apply for a whitelist of the buffered data
@@ -12,7 +20,8 @@ pub struct ParserOptionsFilter {
 impl ParserOptions {
     pub fn new_with_filter(filter: ParserOptionsFilter) -> Self {
         ParserOptions {
-            filter
+            filter,
+            ..Default::default()
         }
     }
 
@@ -21,7 +30,8 @@ impl ParserOptions {
             filter: ParserOptionsFilter {
                 output_whitelist,
                 buffer_whitelist: None,
-            }
+            },
+            ..Default::default()
         }
     }
 
@@ -30,7 +40,8 @@ impl ParserOptions {
             filter: ParserOptionsFilter {
                 output_whitelist: None,
                 buffer_whitelist,
-            }
+            },
+            ..Default::default()
         }
     }
 
@@ -42,7 +53,99 @@ impl ParserOptions {
             filter: ParserOptionsFilter {
                 output_whitelist,
                 buffer_whitelist,
-            }
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Sets the encoding of the input. By default, UTF-8 is expected, and a leading BOM is stripped
+    pub fn with_input_encoding(mut self, input_encoding: InputEncoding) -> Self {
+        self.input_encoding = input_encoding;
+        self
+    }
+
+    /// Enables the recovery mode, in which malformed input does not stop the parsing :
+    /// - invalid escape sequences and invalid UTF-8 in strings are replaced with U+FFFD
+    /// - a malformed element is skipped up to the next ',', '}' or ']' at the same depth
+    /// - characters before the root value starts are ignored
+    /// Each recovery is recorded as a warning, with its position
+    pub fn with_recovery(mut self, recovery: bool) -> Self {
+        self.recovery = recovery;
+        self
+    }
+
+    /// Makes add_char and feed flush the string in progress by themselves, following the given policy
+    pub fn with_flush_policy(mut self, flush_policy: FlushPolicy) -> Self {
+        self.flush_policy = flush_policy;
+        self
+    }
+
+    /// Prevents flush() from splitting grapheme clusters (ZWJ emoji sequences, flags, combining accents...) across two rows
+    /// The last grapheme cluster of a string is only written once more characters have arrived, or when the string ends
+    pub fn with_grapheme_safe_flush(mut self, grapheme_safe_flush: bool) -> Self {
+        self.grapheme_safe_flush = grapheme_safe_flush;
+        self
+    }
+}
+
+/// Decides when the string in progress is flushed automatically while adding bytes
+/// Manual flush() calls remain possible with any policy
+#[derive(Default, Clone)]
+pub enum FlushPolicy {
+    #[default]
+    Manual, // Only flush when flush() is called
+    MaxBufferedBytes(usize), // Flush as soon as the string in progress holds that many bytes
+    WordBoundary, // Flush after each whitespace
+    SentenceBoundary, // Flush after sentence ending punctuation followed by whitespace, CJK full stops, or newlines
+    Interval { // Flush when the oldest unflushed bytes have been waiting for this long. Checked when bytes are added, and by poll_flush()
+        interval: Duration,
+        clock: SharedClock,
+    },
+}
+
+impl FlushPolicy {
+    /// Interval policy using the system monotonic clock
+    pub fn interval(interval: Duration) -> Self {
+        FlushPolicy::Interval {
+            interval,
+            clock: Shared::new(SystemClock::new()),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Source of time for the interval flush policy, which can be replaced to control time in tests
+pub trait Clock: Debug {
+    /// Monotonic time elapsed since an arbitrary, fixed origin
+    fn now(&self) -> Duration;
+}
+
+#[cfg(not(feature = "sync"))]
+pub type SharedClock = Shared<dyn Clock>;
+#[cfg(feature = "sync")]
+pub type SharedClock = Shared<dyn Clock + Send + Sync>;
+
+/// Clock based on std::time::Instant
+#[derive(Debug, Clone)]
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now()
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
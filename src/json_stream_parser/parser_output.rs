@@ -1,60 +1,276 @@
-use std::rc::Rc;
+use crate::{json_key_path::JsonKeyPath, Shared};
 
+use std::io;
+
+use serde::Serialize;
 use serde_json::Value;
 
 use super::status::Status;
 
 pub mod stream_protocol_output;
 pub mod parser_output_none;
+pub mod json_patch_output;
+pub mod protocol_row_output;
+pub mod tee_output;
+pub mod closure_output;
+pub mod path_protocol_output;
+pub mod sse_output;
+pub mod replay_output;
+pub mod binary_protocol_output;
 
-/// Configurable output for the parser, allowing to return a custom output at specific parser events
+/// Configurable output for the parser, allowing to write a custom output at specific parser events
+/// Every callback writes into the output buffer supplied by the caller, so that one buffer can be reused for many rows
+/// Callbacks take the output mutably, so that it may keep state across events (ex: JsonPatchOutput)
+/// Key paths locate the value within the document, their depth being the nesting level (see JsonKeyPath::depth)
 pub trait ParserOutputTrait {
-    fn new() -> Self;
-
     /// Fires when the first character is read
-    /// Status is the detected status of the value type identified by the first character
+    /// Kind is the one of the root value, identified by the first character
     /// Might not be defined yet (for ex in case of whitespace as first character)
     fn on_init(
-        &self,
+        &mut self,
+        output: &mut String,
         current_node_idx: usize,
-        next_status: Option<&Status>
-    ) -> Option<String>;
+        value_kind: Option<ValueKind>
+    );
 
-    /// Trigger when a status has been completed
+    /// Trigger when a value has been completed
+    /// Parent kind is None for the root value
+    /// Key path is the one of the completed value
+    /// When the completed value is a basic type (null, bool, number) within an object or an array,
+    /// current_node_idx is the index of the parent
     fn on_status_complete(
-        &self,
-        parent_status: &Status,
-        current_status: &Status,
+        &mut self,
+        output: &mut String,
+        parent_kind: Option<ValueKind>,
+        current_kind: ValueKind,
         current_node_idx: usize,
-        output_value: Option<Rc<Value>>
-    ) -> Option<String>;
+        key_path: &JsonKeyPath,
+        output_value: Option<Shared<Value>>
+    );
 
     /// Trigger when an object key has been parsed
+    /// Key path is the one of the object
     fn on_object_key_complete(
-        &self,
-        key: &String
-    ) -> Option<String>;
+        &mut self,
+        output: &mut String,
+        key: &String,
+        key_path: &JsonKeyPath
+    );
 
     /// Trigger when flush has been requested
+    /// Key path is the one of the string being flushed
     fn on_flush(
-        &self,
+        &mut self,
+        output: &mut String,
         current_node_idx: usize,
+        key_path: &JsonKeyPath,
         flush_output: &Value
-    ) -> Option<String>;
+    );
+
+    /// Trigger when a node with its own index is complete : a string, an array, an object, or the root whatever its type
+    /// No more row will be written for current_node_idx afterwards
+    fn on_node_complete(
+        &mut self,
+        output: &mut String,
+        current_kind: ValueKind,
+        current_node_idx: usize,
+        key_path: &JsonKeyPath
+    );
+
+    /// Trigger when parsing fails, either on an invalid character or when finishing a truncated document
+    /// No more row will be written afterwards
+    fn on_error(
+        &mut self,
+        output: &mut String,
+        message: &str
+    );
+
+    /// Trigger when finish_into() has been called on a complete document
+    fn on_finish(
+        &mut self,
+        output: &mut String
+    );
 
     /// Trigger when a new node has been added to an object or an array
+    /// Key path is the one of the new node
+    fn on_new_subnode(
+        &mut self,
+        output: &mut String,
+        parent_node: ParentNode,
+        current_kind: ValueKind,
+        parent_node_idx: usize,
+        current_node_idx: usize,
+        key_path: &JsonKeyPath
+    );
+}
+
+/// Output forwarding every event to inner outputs, ex: to post-process what they write (see SseOutput)
+/// Implementing it implements ParserOutputTrait
+pub trait ParserOutputWrapper {
+    type Inner: ParserOutputTrait + ?Sized;
+
+    /// Calls the event on every inner output, giving each the output it writes into
+    fn forward_event(&mut self, output: &mut String, event: impl FnMut(&mut Self::Inner, &mut String));
+}
+
+impl<W> ParserOutputTrait for W
+where
+    W: ParserOutputWrapper
+{
+    #[inline(always)]
+    fn on_init(&mut self, output: &mut String, current_node_idx: usize, value_kind: Option<ValueKind>) {
+        self.forward_event(output, |inner, output| inner.on_init(output, current_node_idx, value_kind));
+    }
+
+    #[inline(always)]
+    fn on_status_complete(
+        &mut self,
+        output: &mut String,
+        parent_kind: Option<ValueKind>,
+        current_kind: ValueKind,
+        current_node_idx: usize,
+        key_path: &JsonKeyPath,
+        output_value: Option<Shared<Value>>
+    ) {
+        self.forward_event(output, |inner, output| {
+            inner.on_status_complete(output, parent_kind, current_kind, current_node_idx, key_path, output_value.clone());
+        });
+    }
+
+    #[inline(always)]
+    fn on_object_key_complete(&mut self, output: &mut String, key: &String, key_path: &JsonKeyPath) {
+        self.forward_event(output, |inner, output| inner.on_object_key_complete(output, key, key_path));
+    }
+
+    #[inline(always)]
+    fn on_flush(&mut self, output: &mut String, current_node_idx: usize, key_path: &JsonKeyPath, flush_output: &Value) {
+        self.forward_event(output, |inner, output| inner.on_flush(output, current_node_idx, key_path, flush_output));
+    }
+
+    #[inline(always)]
+    fn on_node_complete(&mut self, output: &mut String, current_kind: ValueKind, current_node_idx: usize, key_path: &JsonKeyPath) {
+        self.forward_event(output, |inner, output| inner.on_node_complete(output, current_kind, current_node_idx, key_path));
+    }
+
+    #[inline(always)]
+    fn on_error(&mut self, output: &mut String, message: &str) {
+        self.forward_event(output, |inner, output| inner.on_error(output, message));
+    }
+
+    #[inline(always)]
+    fn on_finish(&mut self, output: &mut String) {
+        self.forward_event(output, |inner, output| inner.on_finish(output));
+    }
+
+    #[inline(always)]
     fn on_new_subnode(
-        &self,
+        &mut self,
+        output: &mut String,
         parent_node: ParentNode,
-        current_status: &Status,
+        current_kind: ValueKind,
         parent_node_idx: usize,
         current_node_idx: usize,
-    ) -> Option<String>;
+        key_path: &JsonKeyPath
+    ) {
+        self.forward_event(output, |inner, output| {
+            inner.on_new_subnode(output, parent_node, current_kind, parent_node_idx, current_node_idx, key_path);
+        });
+    }
+}
+
+/// Type of a JSON value, as given to parser outputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object
+}
+
+impl ValueKind {
+    /// Whether the value is written at once, without an index of its own when nested in an object or an array
+    pub fn is_basic(&self) -> bool {
+        matches!(self, ValueKind::Null | ValueKind::Bool | ValueKind::Number)
+    }
+
+    /// Empty value a string, an array or an object starts with, None for basic values which are written at once
+    pub fn init_value(&self) -> Option<Value> {
+        match self {
+            ValueKind::String => Some(Value::String(String::new())),
+            ValueKind::Array => Some(Value::Array(Vec::new())),
+            ValueKind::Object => Some(Value::Object(serde_json::Map::new())),
+            ValueKind::Null | ValueKind::Bool | ValueKind::Number => None
+        }
+    }
+
+    /// None when the type is not known yet, or once the value is done
+    pub(crate) fn from_status(status: &Status) -> Option<Self> {
+        match status {
+            Status::Null(_) => Some(ValueKind::Null),
+            Status::Bool(_) => Some(ValueKind::Bool),
+            Status::Number(_) => Some(ValueKind::Number),
+            Status::String(_) => Some(ValueKind::String),
+            Status::Array(_) => Some(ValueKind::Array),
+            Status::Object(_) => Some(ValueKind::Object),
+            Status::None(_) | Status::Done(_) => None
+        }
+    }
 }
 
 /// Helper enum to identify cases of an object or array node
 /// The attribute is the key
-pub enum ParentNode {
-    Object(String),
+#[derive(Debug, Clone, Copy)]
+pub enum ParentNode<'a> {
+    Object(&'a str),
     Array(usize)
-}
\ No newline at end of file
+}
+
+/// Adapter allowing to serialize JSON straight into a String
+pub struct StringWriter<'a> {
+    inner: &'a mut String,
+}
+
+impl<'a> StringWriter<'a> {
+    pub fn new(inner: &'a mut String) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a> io::Write for StringWriter<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Safety: serde_json only emits valid utf8 when using
+        // the default formatter.
+        let s = unsafe { std::str::from_utf8_unchecked(buf) };
+        self.inner.push_str(s);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Serializes a JSON value at the end of the output
+#[inline(always)]
+pub fn write_json<T: ?Sized + Serialize>(output: &mut String, value: &T) {
+    // Writing into a String cannot fail, and serializing a Value cannot fail either
+    serde_json::to_writer(StringWriter::new(output), value).unwrap();
+}
+
+/// Serializes a string as JSON at the end of the output, as write_json does
+/// Strings without any character to escape, the most common ones, are copied at once
+#[inline]
+pub fn write_json_str(output: &mut String, value: &str) {
+    // Not stopping at the first match lets the check run over several bytes at once
+    let needs_escaping = value.bytes().fold(false, |needs_escaping, c| needs_escaping | (c < 0x20) | (c == b'"') | (c == b'\\'));
+    if needs_escaping {
+        write_json(output, value);
+        return;
+    }
+    output.reserve(value.len() + 2);
+    output.push('"');
+    output.push_str(value);
+    output.push('"');
+}
@@ -0,0 +1,65 @@
+use crate::stream_protocol::ProtocolHeader;
+use super::{protocol_row_output::ProtocolRowOutput, ParserOutputWrapper};
+
+/// Writes the rows of the KurocoEdge stream protocol in their binary form (see BinaryRowEncoder)
+/// The text output is left untouched : bytes are collected until taken with take_bytes()
+#[derive(Clone)]
+pub struct BinaryProtocolOutput {
+    rows: ProtocolRowOutput<Vec<u8>>,
+}
+
+impl ParserOutputWrapper for BinaryProtocolOutput {
+    type Inner = ProtocolRowOutput<Vec<u8>>;
+
+    #[inline(always)]
+    fn forward_event(&mut self, output: &mut String, mut event: impl FnMut(&mut Self::Inner, &mut String)) {
+        event(&mut self.rows, output);
+    }
+}
+
+impl BinaryProtocolOutput {
+    pub fn new() -> Self {
+        Self {
+            rows: ProtocolRowOutput::with_sink(Vec::new())
+        }
+    }
+
+    /// Writes a close row once a string, an array, an object or the root is complete
+    pub fn with_completion_markers(mut self) -> Self {
+        self.rows = self.rows.with_completion_markers();
+        self
+    }
+
+    /// Writes a terminal row once the stream ends : end when the document is complete, error when parsing fails
+    pub fn with_end_rows(mut self) -> Self {
+        self.rows = self.rows.with_end_rows();
+        self
+    }
+
+    /// Writes a header row before the first one
+    pub fn with_header(mut self) -> Self {
+        self.rows = self.rows.with_header();
+        self
+    }
+
+    /// Header describing the rows written by this output
+    pub fn protocol_header(&self, root_idx: usize) -> ProtocolHeader {
+        self.rows.protocol_header(root_idx)
+    }
+
+    /// Bytes written so far and not taken yet
+    pub fn bytes(&self) -> &[u8] {
+        self.rows.sink()
+    }
+
+    /// Moves out the bytes written so far
+    pub fn take_bytes(&mut self) -> Vec<u8> {
+        std::mem::take(self.rows.sink_mut())
+    }
+}
+
+impl Default for BinaryProtocolOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
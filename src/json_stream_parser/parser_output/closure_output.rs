@@ -0,0 +1,181 @@
+use crate::{json_key_path::JsonKeyPath, Shared};
+
+use serde_json::Value;
+
+use super::{ParentNode, ParserOutputTrait, ValueKind};
+
+/// Callback types, taking the same arguments as the matching ParserOutputTrait method
+/// With the "sync" feature, callbacks must be Send + Sync so that the parser can be moved across threads
+macro_rules! callback_type {
+    ($name:ident, $($arg:ty),*) => {
+        #[cfg(not(feature = "sync"))]
+        pub type $name = Shared<dyn Fn($($arg),*)>;
+        #[cfg(feature = "sync")]
+        pub type $name = Shared<dyn Fn($($arg),*) + Send + Sync>;
+    };
+}
+
+callback_type!(OnInitCallback, &mut String, usize, Option<ValueKind>);
+callback_type!(OnStatusCompleteCallback, &mut String, Option<ValueKind>, ValueKind, usize, &JsonKeyPath, Option<Shared<Value>>);
+callback_type!(OnObjectKeyCompleteCallback, &mut String, &String, &JsonKeyPath);
+callback_type!(OnFlushCallback, &mut String, usize, &JsonKeyPath, &Value);
+callback_type!(OnNodeCompleteCallback, &mut String, ValueKind, usize, &JsonKeyPath);
+callback_type!(OnErrorCallback, &mut String, &str);
+callback_type!(OnFinishCallback, &mut String);
+callback_type!(OnNewSubnodeCallback, &mut String, ParentNode, ValueKind, usize, usize, &JsonKeyPath);
+
+/// Bounds of the closures given to ClosureOutput
+#[cfg(not(feature = "sync"))]
+pub trait CallbackBounds: 'static {}
+#[cfg(not(feature = "sync"))]
+impl<T: 'static> CallbackBounds for T {}
+#[cfg(feature = "sync")]
+pub trait CallbackBounds: Send + Sync + 'static {}
+#[cfg(feature = "sync")]
+impl<T: Send + Sync + 'static> CallbackBounds for T {}
+
+/// Output built from closures, for the callbacks of interest only : the other ones write nothing
+/// Ex: `ClosureOutput::new().with_on_error(|output, message| output.push_str(message))`
+/// Closures are shared by forks of the parser, so state they keep must live in a Cell or a RefCell (or a Mutex with the "sync" feature)
+#[derive(Clone, Default)]
+pub struct ClosureOutput {
+    on_init: Option<OnInitCallback>,
+    on_status_complete: Option<OnStatusCompleteCallback>,
+    on_object_key_complete: Option<OnObjectKeyCompleteCallback>,
+    on_flush: Option<OnFlushCallback>,
+    on_node_complete: Option<OnNodeCompleteCallback>,
+    on_error: Option<OnErrorCallback>,
+    on_finish: Option<OnFinishCallback>,
+    on_new_subnode: Option<OnNewSubnodeCallback>,
+}
+
+impl ClosureOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_on_init(mut self, callback: impl Fn(&mut String, usize, Option<ValueKind>) + CallbackBounds) -> Self {
+        self.on_init = Some(Shared::new(callback));
+        self
+    }
+
+    pub fn with_on_status_complete(
+        mut self,
+        callback: impl Fn(&mut String, Option<ValueKind>, ValueKind, usize, &JsonKeyPath, Option<Shared<Value>>) + CallbackBounds
+    ) -> Self {
+        self.on_status_complete = Some(Shared::new(callback));
+        self
+    }
+
+    pub fn with_on_object_key_complete(mut self, callback: impl Fn(&mut String, &String, &JsonKeyPath) + CallbackBounds) -> Self {
+        self.on_object_key_complete = Some(Shared::new(callback));
+        self
+    }
+
+    pub fn with_on_flush(mut self, callback: impl Fn(&mut String, usize, &JsonKeyPath, &Value) + CallbackBounds) -> Self {
+        self.on_flush = Some(Shared::new(callback));
+        self
+    }
+
+    pub fn with_on_node_complete(mut self, callback: impl Fn(&mut String, ValueKind, usize, &JsonKeyPath) + CallbackBounds) -> Self {
+        self.on_node_complete = Some(Shared::new(callback));
+        self
+    }
+
+    pub fn with_on_error(mut self, callback: impl Fn(&mut String, &str) + CallbackBounds) -> Self {
+        self.on_error = Some(Shared::new(callback));
+        self
+    }
+
+    pub fn with_on_finish(mut self, callback: impl Fn(&mut String) + CallbackBounds) -> Self {
+        self.on_finish = Some(Shared::new(callback));
+        self
+    }
+
+    pub fn with_on_new_subnode(
+        mut self,
+        callback: impl Fn(&mut String, ParentNode, ValueKind, usize, usize, &JsonKeyPath) + CallbackBounds
+    ) -> Self {
+        self.on_new_subnode = Some(Shared::new(callback));
+        self
+    }
+}
+
+impl ParserOutputTrait for ClosureOutput {
+    #[inline(always)]
+    fn on_init(&mut self, output: &mut String, current_node_idx: usize, value_kind: Option<ValueKind>) {
+        if let Some(callback) = &self.on_init {
+            callback(output, current_node_idx, value_kind);
+        }
+    }
+
+    #[inline(always)]
+    fn on_status_complete(
+        &mut self,
+        output: &mut String,
+        parent_kind: Option<ValueKind>,
+        current_kind: ValueKind,
+        current_node_idx: usize,
+        key_path: &JsonKeyPath,
+        output_value: Option<Shared<Value>>
+    ) {
+        if let Some(callback) = &self.on_status_complete {
+            callback(output, parent_kind, current_kind, current_node_idx, key_path, output_value);
+        }
+    }
+
+    #[inline(always)]
+    fn on_object_key_complete(
+        &mut self,
+        output: &mut String,
+        key: &String,
+        key_path: &JsonKeyPath
+    ) {
+        if let Some(callback) = &self.on_object_key_complete {
+            callback(output, key, key_path);
+        }
+    }
+
+    #[inline(always)]
+    fn on_flush(&mut self, output: &mut String, current_node_idx: usize, key_path: &JsonKeyPath, flush_output: &Value) {
+        if let Some(callback) = &self.on_flush {
+            callback(output, current_node_idx, key_path, flush_output);
+        }
+    }
+
+    #[inline(always)]
+    fn on_node_complete(&mut self, output: &mut String, current_kind: ValueKind, current_node_idx: usize, key_path: &JsonKeyPath) {
+        if let Some(callback) = &self.on_node_complete {
+            callback(output, current_kind, current_node_idx, key_path);
+        }
+    }
+
+    #[inline(always)]
+    fn on_error(&mut self, output: &mut String, message: &str) {
+        if let Some(callback) = &self.on_error {
+            callback(output, message);
+        }
+    }
+
+    #[inline(always)]
+    fn on_finish(&mut self, output: &mut String) {
+        if let Some(callback) = &self.on_finish {
+            callback(output);
+        }
+    }
+
+    #[inline(always)]
+    fn on_new_subnode(
+        &mut self,
+        output: &mut String,
+        parent_node: ParentNode,
+        current_kind: ValueKind,
+        parent_node_idx: usize,
+        current_node_idx: usize,
+        key_path: &JsonKeyPath
+    ) {
+        if let Some(callback) = &self.on_new_subnode {
+            callback(output, parent_node, current_kind, parent_node_idx, current_node_idx, key_path);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use crate::{json_key_path::JsonKeyPath, Shared};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{write_json, ParentNode, ParserOutputTrait, ValueKind};
+
+pub const OPERATION_ADD: &str = "add";
+pub const OPERATION_REPLACE: &str = "replace";
+pub const OPERATION_APPEND: &str = "append"; // Not part of RFC 6902 : appends the value to the string at path
+
+/// How the growth of a string is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StringGrowth {
+    /// Custom `append` operation with the new part only
+    #[default]
+    Append,
+    /// Standard `replace` operation with the whole string received so far, for off-the-shelf JSON Patch clients
+    Replace,
+}
+
+#[derive(Serialize)]
+struct PatchOperation<'a, T: ?Sized + Serialize> {
+    op: &'static str,
+    path: &'a str,
+    value: &'a T,
+}
+
+/// Writes JSON Patch (RFC 6902) operations, one JSON object per line, with JSON Pointer paths
+/// Ex: `{"op":"add","path":"/choices/0","value":{}}`
+#[derive(Clone, Default)]
+pub struct JsonPatchOutput {
+    string_growth: StringGrowth,
+    strings: HashMap<usize, String>, // With StringGrowth::Replace, content received so far of the strings in progress
+}
+
+impl ParserOutputTrait for JsonPatchOutput {
+    #[inline(always)]
+    fn on_init(&mut self, output: &mut String, _current_node_idx: usize, value_kind: Option<ValueKind>) {
+        // A basic root value is added once complete
+        if let Some(init_value) = value_kind.and_then(|kind| kind.init_value()) {
+            self.write_operation(output, OPERATION_ADD, "", &init_value);
+        }
+    }
+
+    #[inline(always)]
+    fn on_status_complete(
+        &mut self,
+        output: &mut String,
+        _parent_kind: Option<ValueKind>,
+        current_kind: ValueKind,
+        current_node_idx: usize,
+        key_path: &JsonKeyPath,
+        output_value: Option<Shared<Value>>
+    ) {
+        let Some(value) = output_value else {
+            return;
+        };
+        match current_kind {
+            ValueKind::Null | ValueKind::Bool | ValueKind::Number => {
+                self.write_operation(output, OPERATION_ADD, &key_path.to_json_pointer(), value.as_ref());
+            },
+            ValueKind::String => {
+                if let Value::String(part) = value.as_ref() {
+                    self.write_string_growth(output, current_node_idx, key_path, part);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    #[inline(always)]
+    fn on_object_key_complete(
+        &mut self,
+        _output: &mut String,
+        _key: &String,
+        _key_path: &JsonKeyPath
+    ) {
+    }
+
+    #[inline(always)]
+    fn on_flush(&mut self, output: &mut String, current_node_idx: usize, key_path: &JsonKeyPath, flush_output: &Value) {
+        if let Value::String(part) = flush_output {
+            self.write_string_growth(output, current_node_idx, key_path, part);
+        }
+    }
+
+    #[inline(always)]
+    fn on_node_complete(&mut self, _output: &mut String, _current_kind: ValueKind, current_node_idx: usize, _key_path: &JsonKeyPath) {
+        self.strings.remove(&current_node_idx);
+    }
+
+    #[inline(always)]
+    fn on_error(&mut self, _output: &mut String, _message: &str) {
+    }
+
+    #[inline(always)]
+    fn on_finish(&mut self, _output: &mut String) {
+    }
+
+    #[inline(always)]
+    fn on_new_subnode(
+        &mut self,
+        output: &mut String,
+        _parent_node: ParentNode,
+        current_kind: ValueKind,
+        _parent_node_idx: usize,
+        _current_node_idx: usize,
+        key_path: &JsonKeyPath
+    ) {
+        // Array elements are added at their index, which is always the end of the array
+        if let Some(init_value) = current_kind.init_value() {
+            self.write_operation(output, OPERATION_ADD, &key_path.to_json_pointer(), &init_value);
+        }
+    }
+}
+
+impl JsonPatchOutput {
+    pub fn new() -> Self {
+        Self {
+            string_growth: StringGrowth::default(),
+            strings: HashMap::new()
+        }
+    }
+
+    pub fn with_string_growth(mut self, string_growth: StringGrowth) -> Self {
+        self.string_growth = string_growth;
+        self
+    }
+
+    fn write_string_growth(&mut self, output: &mut String, current_node_idx: usize, key_path: &JsonKeyPath, part: &str) {
+        if part.is_empty() {
+            return;
+        }
+        let path = key_path.to_json_pointer();
+        match self.string_growth {
+            StringGrowth::Append => self.write_operation(output, OPERATION_APPEND, &path, part),
+            StringGrowth::Replace => {
+                let string = self.strings.entry(current_node_idx).or_default();
+                string.push_str(part);
+                let operation = PatchOperation { op: OPERATION_REPLACE, path: &path, value: string.as_str() };
+                write_json(output, &operation);
+                output.push('\n');
+            },
+        }
+    }
+
+    #[inline(always)]
+    fn write_operation<T: ?Sized + Serialize>(&self, output: &mut String, op: &'static str, path: &str, value: &T) {
+        write_json(output, &PatchOperation { op, path, value });
+        output.push('\n');
+    }
+}
@@ -1,55 +1,70 @@
-use std::rc::Rc;
+use crate::{json_key_path::JsonKeyPath, Shared};
 
 use serde_json::Value;
 
-use crate::json_stream_parser::status::Status;
-use super::{ParentNode, ParserOutputTrait};
+use super::{ParentNode, ParserOutputTrait, ValueKind};
 
 /// Implementation of parser with no output (for usage when only buffered data is needed)
+#[derive(Clone)]
 pub struct ParserOutputNone;
 
-impl ParserOutputTrait for ParserOutputNone {
-    fn new() -> Self {
+impl ParserOutputNone {
+    pub fn new() -> Self {
         Self {}
     }
+}
 
+impl ParserOutputTrait for ParserOutputNone {
     #[inline(always)]
-    fn on_init(&self, _current_node_idx: usize, _next_status: Option<&Status>) -> Option<String> {
-       None
+    fn on_init(&mut self, _output: &mut String, _current_node_idx: usize, _value_kind: Option<ValueKind>) {
     }
 
     #[inline(always)]
     fn on_status_complete(
-        &self,
-        _parent_status: &Status,
-        _current_status: &Status,
+        &mut self,
+        _output: &mut String,
+        _parent_kind: Option<ValueKind>,
+        _current_kind: ValueKind,
         _current_node_idx: usize,
-        _output_value: Option<Rc<Value>>
-    ) -> Option<String> {
-        None
+        _key_path: &JsonKeyPath,
+        _output_value: Option<Shared<Value>>
+    ) {
     }
 
     #[inline(always)]
     fn on_object_key_complete(
-        &self,
-        _key: &String
-    ) -> Option<String> {
-        None
+        &mut self,
+        _output: &mut String,
+        _key: &String,
+        _key_path: &JsonKeyPath
+    ) {
+    }
+
+    #[inline(always)]
+    fn on_flush(&mut self, _output: &mut String, _current_node_idx: usize, _key_path: &JsonKeyPath, _flush_output: &Value) {
+    }
+
+    #[inline(always)]
+    fn on_node_complete(&mut self, _output: &mut String, _current_kind: ValueKind, _current_node_idx: usize, _key_path: &JsonKeyPath) {
+    }
+
+    #[inline(always)]
+    fn on_error(&mut self, _output: &mut String, _message: &str) {
     }
 
     #[inline(always)]
-    fn on_flush(&self, _current_node_idx: usize, _flush_output: &Value) -> Option<String> {
-        None
+    fn on_finish(&mut self, _output: &mut String) {
     }
 
     #[inline(always)]
     fn on_new_subnode(
-        &self,
+        &mut self,
+        _output: &mut String,
         _parent_node: ParentNode,
-        _current_status: &Status,
+        _current_kind: ValueKind,
         _parent_node_idx: usize,
         _current_node_idx: usize,
-    ) -> Option<String> {
-        None
+        _key_path: &JsonKeyPath
+    ) {
     }
-}
\ No newline at end of file
+}
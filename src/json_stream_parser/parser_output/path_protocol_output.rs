@@ -0,0 +1,241 @@
+use crate::{json_key_path::JsonKeyPath, Shared};
+
+use serde_json::Value;
+
+use crate::json_stream_parser::error::ParseError;
+use super::{write_json, ParentNode, ParserOutputTrait, ValueKind};
+
+pub const PATH_ROOT: &str = ".";
+pub const PATH_SEPARATOR: char = '.';
+pub const PATH_OPERATOR_ASSIGN: &str = "=";
+pub const PATH_OPERATOR_APPEND: &str = "+=";
+
+/// Human readable rows addressed by key path instead of node index, for logs and tests
+/// Ex: `choices.0.delta.content += "Hello"`
+/// The root is written `.`, and keys which could be mistaken for a path (empty, with dots, spaces or quotes) are written as JSON strings
+/// See PathProtocolDecoder to rebuild the value from the rows
+#[derive(Clone, Default)]
+pub struct PathProtocolOutput;
+
+impl PathProtocolOutput {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn write_row(output: &mut String, key_path: &JsonKeyPath, operator: &'static str, value: &Value) {
+        write_path(output, key_path);
+        output.push(' ');
+        output.push_str(operator);
+        output.push(' ');
+        write_json(output, value);
+        output.push('\n');
+    }
+}
+
+impl ParserOutputTrait for PathProtocolOutput {
+    #[inline(always)]
+    fn on_init(&mut self, output: &mut String, _current_node_idx: usize, value_kind: Option<ValueKind>) {
+        // A basic root value is assigned once complete
+        if let Some(init_value) = value_kind.and_then(|kind| kind.init_value()) {
+            Self::write_row(output, &JsonKeyPath::new(), PATH_OPERATOR_ASSIGN, &init_value);
+        }
+    }
+
+    #[inline(always)]
+    fn on_status_complete(
+        &mut self,
+        output: &mut String,
+        _parent_kind: Option<ValueKind>,
+        current_kind: ValueKind,
+        _current_node_idx: usize,
+        key_path: &JsonKeyPath,
+        output_value: Option<Shared<Value>>
+    ) {
+        let Some(value) = output_value else {
+            return;
+        };
+        match (current_kind, value.as_ref()) {
+            (ValueKind::Null | ValueKind::Bool | ValueKind::Number, value) => Self::write_row(output, key_path, PATH_OPERATOR_ASSIGN, value),
+            (ValueKind::String, Value::String(part)) if !part.is_empty() => Self::write_row(output, key_path, PATH_OPERATOR_APPEND, &value),
+            _ => {}
+        }
+    }
+
+    #[inline(always)]
+    fn on_object_key_complete(
+        &mut self,
+        _output: &mut String,
+        _key: &String,
+        _key_path: &JsonKeyPath
+    ) {
+    }
+
+    #[inline(always)]
+    fn on_flush(&mut self, output: &mut String, _current_node_idx: usize, key_path: &JsonKeyPath, flush_output: &Value) {
+        if let Value::String(part) = flush_output {
+            if !part.is_empty() {
+                Self::write_row(output, key_path, PATH_OPERATOR_APPEND, flush_output);
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn on_node_complete(&mut self, _output: &mut String, _current_kind: ValueKind, _current_node_idx: usize, _key_path: &JsonKeyPath) {
+    }
+
+    #[inline(always)]
+    fn on_error(&mut self, _output: &mut String, _message: &str) {
+    }
+
+    #[inline(always)]
+    fn on_finish(&mut self, _output: &mut String) {
+    }
+
+    #[inline(always)]
+    fn on_new_subnode(
+        &mut self,
+        output: &mut String,
+        _parent_node: ParentNode,
+        current_kind: ValueKind,
+        _parent_node_idx: usize,
+        _current_node_idx: usize,
+        key_path: &JsonKeyPath
+    ) {
+        // Unlike StreamProtocolOutput, the path is enough to address the new node : no reference in the parent
+        if let Some(init_value) = current_kind.init_value() {
+            Self::write_row(output, key_path, PATH_OPERATOR_ASSIGN, &init_value);
+        }
+    }
+}
+
+/// Writes the path of the key path, `.` at root
+pub fn write_path(output: &mut String, key_path: &JsonKeyPath) {
+    if key_path.depth() == 0 {
+        output.push_str(PATH_ROOT);
+        return;
+    }
+    for (position, segment) in key_path.segments().enumerate() {
+        if position > 0 {
+            output.push(PATH_SEPARATOR);
+        }
+        let needs_quotes = segment.is_empty() || segment.chars().any(|c| c == PATH_SEPARATOR || c == '"' || c.is_whitespace() || c.is_control());
+        if needs_quotes {
+            write_json(output, segment);
+        } else {
+            output.push_str(segment);
+        }
+    }
+}
+
+/// Reads the path at the start of the row, up to the first space
+/// Returns the keys and the rest of the row
+fn parse_path(row: &str) -> Result<(Vec<String>, &str), ParseError> {
+    if let Some(rest) = row.strip_prefix(PATH_ROOT).filter(|rest| rest.starts_with(' ')) {
+        return Ok((Vec::new(), rest));
+    }
+    let mut segments = Vec::new();
+    let mut rest = row;
+    loop {
+        if rest.starts_with('"') {
+            let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<String>();
+            let segment = stream.next()
+                .ok_or_else(|| ParseError::new(format!("Invalid path : {row}")))?
+                .map_err(|err| ParseError::new(format!("Invalid path : {err}")))?;
+            segments.push(segment);
+            rest = &rest[stream.byte_offset()..];
+        } else {
+            let end = rest.find([PATH_SEPARATOR, ' ']).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(ParseError::new(format!("Invalid path : {row}")));
+            }
+            segments.push(rest[..end].to_string());
+            rest = &rest[end..];
+        }
+        match rest.strip_prefix(PATH_SEPARATOR) {
+            Some(next) => rest = next,
+            None => return Ok((segments, rest)),
+        }
+    }
+}
+
+/// Rebuilds the JSON value from the rows written by PathProtocolOutput, for tests and replays
+#[derive(Default)]
+pub struct PathProtocolDecoder {
+    root: Option<Value>,
+}
+
+impl PathProtocolDecoder {
+    pub fn new() -> Self {
+        Self {
+            root: None
+        }
+    }
+
+    /// Applies every complete row of the text
+    pub fn apply_rows(&mut self, rows: &str) -> Result<(), ParseError> {
+        rows.lines()
+            .filter(|row| !row.is_empty())
+            .try_for_each(|row| self.apply_row(row))
+    }
+
+    /// Applies one row, with or without its trailing new line
+    pub fn apply_row(&mut self, row: &str) -> Result<(), ParseError> {
+        let row = row.strip_suffix('\n').unwrap_or(row);
+        let (segments, rest) = parse_path(row)?;
+        let rest = rest.trim_start();
+        let (append, data) = if let Some(data) = rest.strip_prefix(PATH_OPERATOR_APPEND) {
+            (true, data)
+        } else if let Some(data) = rest.strip_prefix(PATH_OPERATOR_ASSIGN) {
+            (false, data)
+        } else {
+            return Err(ParseError::new(format!("Invalid row operator : {row}")));
+        };
+        let data: Value = serde_json::from_str(data.trim_start()).map_err(|err| ParseError::new(format!("Invalid row data : {err}")))?;
+
+        let Some((last_segment, parent_segments)) = segments.split_last() else {
+            // Root
+            match (append, self.root.as_mut()) {
+                (false, _) => self.root = Some(data),
+                (true, Some(Value::String(existing))) => existing.push_str(data.as_str().unwrap_or_default()),
+                (true, _) => return Err(ParseError::new("Invalid append to root")),
+            }
+            return Ok(());
+        };
+        let mut parent = self.root.as_mut().ok_or_else(|| ParseError::new(format!("Row before the root : {row}")))?;
+        for segment in parent_segments {
+            parent = Self::child_mut(parent, segment).ok_or_else(|| ParseError::new(format!("Unknown path : {row}")))?;
+        }
+        if append {
+            match (Self::child_mut(parent, last_segment), data) {
+                (Some(Value::String(existing)), Value::String(part)) => existing.push_str(&part),
+                _ => return Err(ParseError::new(format!("Invalid append : {row}"))),
+            }
+            return Ok(());
+        }
+        match parent {
+            Value::Object(map) => {
+                map.insert(last_segment.clone(), data);
+            },
+            Value::Array(values) => match last_segment.parse::<usize>() {
+                Ok(idx) if idx < values.len() => values[idx] = data,
+                Ok(idx) if idx == values.len() => values.push(data),
+                _ => return Err(ParseError::new(format!("Invalid array index : {row}"))),
+            },
+            _ => return Err(ParseError::new(format!("Unknown path : {row}"))),
+        }
+        Ok(())
+    }
+
+    fn child_mut<'a>(parent: &'a mut Value, segment: &str) -> Option<&'a mut Value> {
+        match parent {
+            Value::Object(map) => map.get_mut(segment),
+            Value::Array(values) => values.get_mut(segment.parse::<usize>().ok()?),
+            _ => None
+        }
+    }
+
+    /// Current value of the document
+    pub fn value(&self) -> Option<&Value> {
+        self.root.as_ref()
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{json_key_path::JsonKeyPath, Shared};
+
+use serde_json::Value;
+
+use crate::stream_protocol::{protocol_row::{ProtocolRow, ProtocolRowRef, RowSink, RowValueRef}, ProtocolHeader, EXTENSION_COMPLETION_MARKERS, EXTENSION_END_ROWS};
+use super::{ParentNode, ParserOutputTrait, ValueKind};
+
+/// Produces the rows of the KurocoEdge stream protocol as structured values instead of text
+/// Rows are handed to a sink as they are produced (see RowSink)
+/// By default, the text output is left untouched : rows are collected until taken with take_rows()
+/// Allows encoding rows differently (see TextRowEncoder), or inspecting them without parsing strings back
+#[derive(Clone)]
+pub struct ProtocolRowOutput<S = Vec<ProtocolRow>> {
+    completion_markers: bool, // Whether to produce a close row once a node is complete
+    end_rows: bool, // Whether to produce a row when the stream ends, either completed or failed
+    header: bool, // Whether to produce the protocol header before the first row
+    sink: S,
+}
+
+impl<S> ParserOutputTrait for ProtocolRowOutput<S>
+where
+    S: RowSink
+{
+    #[inline(always)]
+    fn on_init(&mut self, output: &mut String, current_node_idx: usize, value_kind: Option<ValueKind>) {
+        if self.header {
+            let header = self.protocol_header(current_node_idx);
+            self.sink.push_row(ProtocolRowRef::header(&header), output);
+        }
+        if let Some(init_value) = value_kind.and_then(|kind| kind.init_value()) {
+            self.sink.push_row(ProtocolRowRef::assign(current_node_idx, &init_value), output);
+        }
+    }
+
+    #[inline(always)]
+    fn on_status_complete(
+        &mut self,
+        output: &mut String,
+        parent_kind: Option<ValueKind>,
+        current_kind: ValueKind,
+        current_node_idx: usize,
+        key_path: &JsonKeyPath,
+        output_value: Option<Shared<Value>>
+    ) {
+        let Some(value) = output_value else {
+            return;
+        };
+        let value = value.as_ref();
+        let row = match (parent_kind, current_kind.is_basic()) {
+            (None, true) => ProtocolRowRef::assign(current_node_idx, value),
+            // Basic values of objects have no index of their own : appended under their key, to be merged into the parent
+            (Some(ValueKind::Object), true) => match key_path.last_segment() {
+                Some(key) => ProtocolRowRef::append(current_node_idx, RowValueRef::KeyValue(key, value)),
+                None => ProtocolRowRef::append(current_node_idx, RowValueRef::Value(value)),
+            },
+            (None | Some(ValueKind::Object | ValueKind::Array), _) => ProtocolRowRef::append(current_node_idx, RowValueRef::Value(value)),
+            _ => unreachable!("Logic error : non covered status combination")
+        };
+        self.sink.push_row(row, output);
+    }
+
+    #[inline(always)]
+    fn on_object_key_complete(
+        &mut self,
+        _output: &mut String,
+        _key: &String,
+        _key_path: &JsonKeyPath
+    ) {
+    }
+
+    #[inline(always)]
+    fn on_flush(&mut self, output: &mut String, current_node_idx: usize, _key_path: &JsonKeyPath, flush_output: &Value) {
+        self.sink.push_row(ProtocolRowRef::append(current_node_idx, RowValueRef::Value(flush_output)), output);
+    }
+
+    #[inline(always)]
+    fn on_node_complete(&mut self, output: &mut String, _current_kind: ValueKind, current_node_idx: usize, _key_path: &JsonKeyPath) {
+        if self.completion_markers {
+            self.sink.push_row(ProtocolRowRef::close(current_node_idx), output);
+        }
+    }
+
+    #[inline(always)]
+    fn on_error(&mut self, output: &mut String, message: &str) {
+        if self.end_rows {
+            self.sink.push_row(ProtocolRowRef::error(message), output);
+        }
+    }
+
+    #[inline(always)]
+    fn on_finish(&mut self, output: &mut String) {
+        if self.end_rows {
+            self.sink.push_row(ProtocolRowRef::end(), output);
+        }
+    }
+
+    #[inline(always)]
+    fn on_new_subnode(
+        &mut self,
+        output: &mut String,
+        parent_node: ParentNode,
+        current_kind: ValueKind,
+        parent_node_idx: usize,
+        current_node_idx: usize,
+        _key_path: &JsonKeyPath
+    ) {
+        let Some(init_value) = current_kind.init_value() else {
+            return;
+        };
+
+        // Double initialization : a new index in the parent, and a new value at that index
+        let reference = match parent_node {
+            ParentNode::Object(key) => RowValueRef::KeyReference(key, current_node_idx),
+            ParentNode::Array(_key) => RowValueRef::Reference(current_node_idx),
+        };
+        self.sink.push_row(ProtocolRowRef::append(parent_node_idx, reference), output);
+        self.sink.push_row(ProtocolRowRef::assign(current_node_idx, &init_value), output);
+    }
+}
+
+impl ProtocolRowOutput {
+    pub fn new() -> Self {
+        Self::with_sink(Vec::new())
+    }
+
+    /// Rows produced so far and not taken yet
+    pub fn rows(&self) -> &[ProtocolRow] {
+        &self.sink
+    }
+
+    /// Moves out the rows produced so far
+    pub fn take_rows(&mut self) -> Vec<ProtocolRow> {
+        std::mem::take(&mut self.sink)
+    }
+
+    /// Removes the rows produced so far, keeping the allocation for the next ones
+    pub fn drain_rows(&mut self) -> std::vec::Drain<'_, ProtocolRow> {
+        self.sink.drain(..)
+    }
+}
+
+impl Default for ProtocolRowOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> ProtocolRowOutput<S>
+where
+    S: RowSink
+{
+    /// Hands the rows to the sink as they are produced, ex: TextRowEncoder writes them into the output of the parser
+    pub fn with_sink(sink: S) -> Self {
+        Self {
+            completion_markers: false,
+            end_rows: false,
+            header: false,
+            sink
+        }
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    /// Produces a close row (ex: `5!`) once a string, an array, an object or the root is complete
+    pub fn with_completion_markers(mut self) -> Self {
+        self.completion_markers = true;
+        self
+    }
+
+    /// Produces a terminal row once the stream ends : end when the document is complete, error when parsing fails
+    pub fn with_end_rows(mut self) -> Self {
+        self.end_rows = true;
+        self
+    }
+
+    /// Produces a header row before the first one
+    pub fn with_header(mut self) -> Self {
+        self.header = true;
+        self
+    }
+
+    /// Header describing the rows produced by this output, regardless of the way they are encoded
+    pub fn protocol_header(&self, root_idx: usize) -> ProtocolHeader {
+        let mut header = ProtocolHeader::new(root_idx);
+        if self.completion_markers {
+            header = header.with_extension(EXTENSION_COMPLETION_MARKERS);
+        }
+        if self.end_rows {
+            header = header.with_extension(EXTENSION_END_ROWS);
+        }
+        header
+    }
+}
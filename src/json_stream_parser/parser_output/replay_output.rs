@@ -0,0 +1,51 @@
+use crate::stream_protocol::{protocol_row::{ProtocolRowRef, RowSink}, replay_log::ReplayLog, text_row_encoder::TextRowEncoder};
+
+/// Records every row produced by ProtocolRowOutput into a ReplayLog, one entry per row whatever the framing around it
+/// Rows are written by the inner sink (typically TextRowEncoder), then to the output of the parser unchanged
+/// Ex: `ProtocolRowOutput::with_sink(ReplayOutput::new(TextRowEncoder::default(), replay_log))`, which SseOutput can wrap
+#[derive(Clone)]
+pub struct ReplayOutput<S = TextRowEncoder> {
+    inner: S,
+    replay_log: ReplayLog,
+    row: String, // Written by the inner sink, before being recorded
+}
+
+impl<S> ReplayOutput<S>
+where
+    S: RowSink
+{
+    pub fn new(inner: S, replay_log: ReplayLog) -> Self {
+        Self {
+            inner,
+            replay_log,
+            row: String::new()
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn replay_log(&self) -> &ReplayLog {
+        &self.replay_log
+    }
+}
+
+impl<S> RowSink for ReplayOutput<S>
+where
+    S: RowSink
+{
+    #[inline(always)]
+    fn push_row(&mut self, row: ProtocolRowRef<'_>, output: &mut String) {
+        self.inner.push_row(row, &mut self.row);
+        if !self.row.is_empty() {
+            self.replay_log.record(self.row.as_str());
+            output.push_str(&self.row);
+            self.row.clear();
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::stream_protocol::sse::SseFramer;
+use super::{ParserOutputTrait, ParserOutputWrapper};
+
+/// Frames every row written by the inner output (typically StreamProtocolOutput) as a Server-Sent Event
+/// Ids keep increasing across calls, so that a client can resume with `Last-Event-ID`
+#[derive(Clone)]
+pub struct SseOutput<O> {
+    inner: O,
+    framer: SseFramer,
+    rows: String, // Written by the inner output, before being framed
+}
+
+impl<O> SseOutput<O>
+where
+    O: ParserOutputTrait
+{
+    pub fn new(inner: O, framer: SseFramer) -> Self {
+        Self {
+            inner,
+            framer,
+            rows: String::new()
+        }
+    }
+
+    pub fn inner(&self) -> &O {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut O {
+        &mut self.inner
+    }
+
+    pub fn framer(&self) -> &SseFramer {
+        &self.framer
+    }
+
+    /// Allows writing keep-alive comments between parser calls (see SseFramer::keep_alive_if_idle)
+    pub fn framer_mut(&mut self) -> &mut SseFramer {
+        &mut self.framer
+    }
+
+    #[inline(always)]
+    fn write_events(&mut self, output: &mut String) {
+        if !self.rows.is_empty() {
+            self.framer.write_row_events(output, &self.rows);
+            self.rows.clear();
+        }
+    }
+}
+
+impl<O> ParserOutputWrapper for SseOutput<O>
+where
+    O: ParserOutputTrait
+{
+    type Inner = O;
+
+    #[inline(always)]
+    fn forward_event(&mut self, output: &mut String, mut event: impl FnMut(&mut O, &mut String)) {
+        event(&mut self.inner, &mut self.rows);
+        self.write_events(output);
+    }
+}
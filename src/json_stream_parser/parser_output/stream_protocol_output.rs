@@ -1,137 +1,75 @@
-use std::rc::Rc;
-
-use serde_json::{json, Value};
-
-use crate::json_stream_parser::status::Status;
-use super::{ParentNode, ParserOutputTrait};
+use crate::stream_protocol::{dialect::ProtocolDialect, text_row_encoder::TextRowEncoder, ProtocolHeader};
+use super::{protocol_row_output::ProtocolRowOutput, ParserOutputWrapper};
 
 /// Implementation of the custom streaming protocol used by KurocoEdge JsonStream
-pub struct StreamProtocolOutput;
+/// Rows are produced by ProtocolRowOutput, and written as text straight into the output by TextRowEncoder
+/// By default literal strings are not escaped : one that looks like a reference (ex: "$ke$5") is read as a reference by consumers
+/// Enable ProtocolDialect::escape_literals (declared by the "escape" extension of the header) when the data may contain such strings
+#[derive(Clone)]
+pub struct StreamProtocolOutput {
+    rows: ProtocolRowOutput<TextRowEncoder>,
+}
 
-pub const STREAM_VAR_PREFIX: &'static str = "$ke$";
-pub const OPERATOR_ASSIGN: &'static str = "=";
-pub const OPERATOR_APPEND: &'static str = "+=";
+pub const STREAM_VAR_PREFIX: &str = "$ke$";
+pub const OPERATOR_ASSIGN: &str = "=";
+pub const OPERATOR_APPEND: &str = "+=";
+pub const OPERATOR_CLOSE: &str = "!";
+// Rows not targeting any node start with "!" instead of an index
+pub const ROW_ERROR: &str = "!error=";
+pub const ROW_END: &str = "!end";
 
-impl ParserOutputTrait for StreamProtocolOutput {
-    fn new() -> Self {
-        Self {}
-    }
+impl ParserOutputWrapper for StreamProtocolOutput {
+    type Inner = ProtocolRowOutput<TextRowEncoder>;
 
     #[inline(always)]
-    fn on_init(&self, current_node_idx: usize, next_status: Option<&Status>) -> Option<String> {
-        match next_status {
-            Some(Status::String(_)) => {
-                Some(self.make_row(current_node_idx, OPERATOR_ASSIGN, r#""""#))
-            }
-            Some(Status::Array(_)) => {
-                Some(self.make_row(current_node_idx, OPERATOR_ASSIGN, "[]"))
-            },
-            Some(Status::Object(_)) => {
-                Some(self.make_row(current_node_idx, OPERATOR_ASSIGN, "{}"))
-            },
-            _ => None
+    fn forward_event(&mut self, output: &mut String, mut event: impl FnMut(&mut Self::Inner, &mut String)) {
+        event(&mut self.rows, output);
+    }
+}
+
+impl StreamProtocolOutput {
+    pub fn new() -> Self {
+        Self {
+            rows: ProtocolRowOutput::with_sink(TextRowEncoder::default())
         }
     }
 
-    #[inline(always)]
-    fn on_status_complete(
-        &self,
-        parent_status: &Status,
-        current_status: &Status,
-        current_node_idx: usize,
-        output_value: Option<Rc<Value>>
-    ) -> Option<String> {
-        let operator = match (parent_status, current_status) {
-            (Status::None(_), Status::Null(_) | Status::Bool(_) | Status::Number(_)) => OPERATOR_ASSIGN,
-            (Status::None(_), _) => OPERATOR_APPEND,
-            (Status::Object(_), _) => OPERATOR_APPEND,
-            (Status::Array(_), _) => OPERATOR_APPEND,
-            _ => unreachable!("Logic error : non covered status combination")
-        };
-        output_value.map(|output| {
-            self.make_row(current_node_idx, operator, output.to_string())
-        })
+    /// Writes a close row (ex: `5!`) once a string, an array, an object or the root is complete
+    /// Clients can then tell that no more row will target that index
+    pub fn with_completion_markers(mut self) -> Self {
+        self.rows = self.rows.with_completion_markers();
+        self
     }
 
-    #[inline(always)]
-    fn on_object_key_complete(
-        &self,
-        _key: &String
-    ) -> Option<String> {
-        None
+    /// Writes a terminal row once the stream ends : `!end` when the document is complete (see JsonStreamParser::finish_into),
+    /// or `!error={"message":"..."}` when parsing fails
+    /// Clients can then tell a finished response from a failed or interrupted one
+    pub fn with_end_rows(mut self) -> Self {
+        self.rows = self.rows.with_end_rows();
+        self
     }
 
-    #[inline(always)]
-    fn on_flush(&self, current_node_idx: usize, flush_output: &Value) -> Option<String> {
-        Some(self.make_row(current_node_idx, OPERATOR_APPEND, flush_output.to_string()))
+    /// Writes a header row before the first one, declaring the protocol version, the root index and the enabled extensions
+    /// See ProtocolHeader::parse on the consumer side
+    pub fn with_header(mut self) -> Self {
+        self.rows = self.rows.with_header();
+        self
     }
 
-    #[inline(always)]
-    fn on_new_subnode(
-        &self,
-        parent_node: ParentNode,
-        current_status: &Status,
-        parent_node_idx: usize,
-        current_node_idx: usize,
-    ) -> Option<String> {
-        let init_row = match parent_node {
-            ParentNode::Object(key) => {
-                self.make_row(
-                    parent_node_idx,
-                    OPERATOR_APPEND,
-                    json!({key: format!("{}{}", STREAM_VAR_PREFIX, current_node_idx)}).to_string(),
-                )
-            },
-            ParentNode::Array(_key) => {
-                self.make_row(
-                    parent_node_idx,
-                    OPERATOR_APPEND,
-                    format!("\"{}{}\"", STREAM_VAR_PREFIX, current_node_idx),
-                )
-            },
-        };
-         
-        match current_status {
-            // For string, we need to initialize the row, as we will be appending parts
-            Status::String(_) => {
-                Some(format!("{}{}", // Double initialization : a new index, and a new object at that index
-                    init_row,
-                    self.make_row(
-                        current_node_idx,
-                        OPERATOR_ASSIGN,
-                        "\"\"",
-                    ),
-                ))
-            }
-            Status::Object(_) => {
-                Some(format!("{}{}", // Double initialization : a new index, and a new object at that index
-                    init_row,
-                    self.make_row(
-                        current_node_idx,
-                        OPERATOR_ASSIGN,
-                        "{}",
-                    ),
-                ))
-            }
-            Status::Array(_) => {
-                Some(format!("{}{}", // Double initialization : a new index, and a new object at that index
-                    init_row,
-                    self.make_row(
-                        current_node_idx,
-                        OPERATOR_ASSIGN,
-                        "[]",
-                    ),
-                ))
-            }
-            Status::Null(_) | Status::Bool(_) | Status::Number(_) => None,
-            _ => unreachable!("Status flow is invalid")
-        }
+    /// Configures the reference prefix, the row terminator, the encoding of indices and the escaping of literal strings
+    pub fn with_dialect(mut self, dialect: ProtocolDialect) -> Self {
+        self.rows.sink_mut().set_dialect(dialect);
+        self
+    }
+
+    /// Header describing the rows written by this output
+    pub fn protocol_header(&self, root_idx: usize) -> ProtocolHeader {
+        self.rows.sink().complete_header(&self.rows.protocol_header(root_idx))
     }
 }
 
-impl StreamProtocolOutput {
-    #[inline(always)]
-    fn make_row(&self, idx: usize, operator: &'static str, data: impl Into<String>) -> String {
-        format!("{}{}{}\n", idx, operator, data.into())
+impl Default for StreamProtocolOutput {
+    fn default() -> Self {
+        Self::new()
     }
-}
\ No newline at end of file
+}
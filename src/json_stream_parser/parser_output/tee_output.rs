@@ -0,0 +1,52 @@
+use super::{ParserOutputTrait, ParserOutputWrapper};
+
+/// Feeds the events of one parse to several outputs
+/// The first output writes into the output of the parser, each of the others into a buffer of its own (see take_secondary_output)
+/// Ex: protocol rows for the client, plus a trace for logs
+pub struct TeeOutput {
+    outputs: Vec<Box<dyn ParserOutputTrait>>,
+    secondary_outputs: Vec<String>, // Written by every output but the first, until taken
+}
+
+impl TeeOutput {
+    pub fn new(outputs: Vec<Box<dyn ParserOutputTrait>>) -> Self {
+        Self {
+            secondary_outputs: vec![String::new(); outputs.len().saturating_sub(1)],
+            outputs
+        }
+    }
+
+    pub fn outputs(&self) -> &[Box<dyn ParserOutputTrait>] {
+        &self.outputs
+    }
+
+    pub fn outputs_mut(&mut self) -> &mut [Box<dyn ParserOutputTrait>] {
+        &mut self.outputs
+    }
+
+    /// Written so far and not taken yet by a secondary output, idx 0 being the second output given to new()
+    /// None when there is no such secondary output
+    pub fn secondary_output(&self, idx: usize) -> Option<&str> {
+        self.secondary_outputs.get(idx).map(String::as_str)
+    }
+
+    /// Moves out what a secondary output has written so far, idx 0 being the second output given to new()
+    pub fn take_secondary_output(&mut self, idx: usize) -> Option<String> {
+        self.secondary_outputs.get_mut(idx).map(std::mem::take)
+    }
+}
+
+impl ParserOutputWrapper for TeeOutput {
+    type Inner = dyn ParserOutputTrait;
+
+    #[inline(always)]
+    fn forward_event(&mut self, output: &mut String, mut event: impl FnMut(&mut Self::Inner, &mut String)) {
+        let mut outputs = self.outputs.iter_mut();
+        if let Some(primary) = outputs.next() {
+            event(primary.as_mut(), output);
+        }
+        for (secondary, secondary_output) in outputs.zip(self.secondary_outputs.iter_mut()) {
+            event(secondary.as_mut(), secondary_output);
+        }
+    }
+}
@@ -1,11 +1,11 @@
-use std::{collections::HashMap, rc::Rc};
-use crate::{json_key_path::JsonKeyPath, json_stream_parser::status::status_array::StatusArray, ref_index_generator::RefIndexGenerator};
+use std::{collections::HashMap, time::Duration};
+use crate::{json_key_path::JsonKeyPath, json_stream_parser::status::status_array::StatusArray, ref_index_generator::RefIndexGenerator, stream_protocol::protocol_row::{ProtocolRow, RowValue}, Shared};
 
 use node::{Node, NodeType};
 use serde_json::{json, Map, Value};
 use value_buffer::ValueBuffer;
 
-use super::{error::ParseError, parser_options::ParserOptions, parser_output::{ParentNode, ParserOutputTrait}, status::{status_none::StatusNone, status_object::{StatusObject, SubStatusObject}}, ParserEvent, Status, StatusTrait};
+use super::{error::{FinishError, ParseError, ParseWarning, Truncation}, parser_options::{FlushPolicy, ParserOptions}, parser_output::{ParentNode, ParserOutputTrait, ValueKind}, status::{status_none::StatusNone, status_object::{StatusObject, SubStatusObject}, status_string::StatusString}, ParserEvent, Status, StatusTrait};
 
 mod node;
 mod value_buffer;
@@ -18,20 +18,35 @@ mod value_buffer;
 pub(crate) struct PartialJsonMapper<F, O> {
     key_path: JsonKeyPath,
     ref_index_generator: RefIndexGenerator,
-    node_map: HashMap<usize, Node>,
+    node_stack: Vec<Node>, // Open nodes only, from root to current : completed nodes are released, so memory scales with nesting depth
     current_node_idx: usize,
     current_status: Status,
     event_map: HashMap<ParserEvent, HashMap<String,Vec<F>>>,
+    deferred_events: Option<Vec<(ParserEvent, JsonKeyPath, Option<Shared<Value>>)>>, // Set in forks : events are recorded, to be handled once committed
     is_done: bool,
     string_value_buffer: String, // Storing the string buffer that persists across flushes. Used by events
     value_buffer: Option<ValueBuffer>,
     parser_options: ParserOptions,
     parser_output: O,
+    pending_flush_since: Option<Duration>, // For the interval flush policy : time at which unflushed bytes were first seen
+    char_pos: usize, // Number of bytes processed so far
+    skipping: Option<SkipState>, // Set in recovery mode while skipping a malformed element
+    last_ignored_pos: Option<usize>, // In recovery mode, position of the last character ignored before the root value
+    warnings: Vec<ParseWarning>,
+    has_failed: bool, // Set once an error has been written : nothing follows it
+}
+
+/// Progress of the recovery mode through a malformed element, looking for the next delimiter at the same depth
+#[derive(Debug, Clone, Default)]
+struct SkipState {
+    depth: usize,
+    in_string: bool,
+    escaping: bool,
 }
 
 impl<F, O> PartialJsonMapper<F, O>
 where
-    F: Fn(Option<Rc<Value>>) -> (),
+    F: Fn(Option<Shared<Value>>) -> (),
     O: ParserOutputTrait
 {
     pub(crate) fn new(
@@ -49,34 +64,41 @@ where
         Self {
             key_path: JsonKeyPath::new(),
             ref_index_generator,
-            node_map: HashMap::new(),
+            node_stack: Vec::new(),
             current_status: Status::None(StatusNone {}),
             current_node_idx,
             event_map: HashMap::new(),
+            deferred_events: None,
             is_done: false,
             string_value_buffer: String::new(),
             value_buffer,
             parser_options,
-            parser_output
+            parser_output,
+            pending_flush_since: None,
+            char_pos: 0,
+            skipping: None,
+            last_ignored_pos: None,
+            warnings: Vec::new(),
+            has_failed: false,
         }
     }
 
     #[inline]
     fn is_ignoring_current_output(&self) -> bool {
-        return self.node_map.get(&self.current_node_idx).map(|node| node.node_ignore_output).unwrap_or(false);
+        return self.node_stack.last().map(|node| node.node_ignore_output).unwrap_or(false);
     }
 
     #[inline]
     fn is_ignoring_current_buffer(&self) -> bool {
-        return self.node_map.get(&self.current_node_idx).map(|node| node.node_ignore_buffer).unwrap_or(false);
+        return self.node_stack.last().map(|node| node.node_ignore_buffer).unwrap_or(false);
     }
 
     /// If return is false, the output should be ignored
     #[inline]
     fn on_event_move_down(&mut self, key: &str) {
-        self.key_path.move_down_object_or_array(key);
+        self.key_path.move_down(key);
         if !self.is_ignoring_current_output() {
-            if let Some(current_node) = self.node_map.get_mut(&self.current_node_idx) {
+            if let Some(current_node) = self.node_stack.last_mut() {
                 // If not ignoring still, confirm filters now
                 if let Some(output_whitelist) = self.parser_options.filter.output_whitelist.as_ref() {
                     if !self.key_path.match_list(output_whitelist.iter().collect(), true) {
@@ -86,7 +108,7 @@ where
             }
         }
         if !self.is_ignoring_current_buffer() {
-            if let Some(current_node) = self.node_map.get_mut(&self.current_node_idx) {
+            if let Some(current_node) = self.node_stack.last_mut() {
                 // If not ignoring still, confirm filters now
                 if let Some(buffer_whitelist) = self.parser_options.filter.buffer_whitelist.as_ref() {
                     if !self.key_path.match_list(buffer_whitelist.iter().collect(), true) {
@@ -96,16 +118,7 @@ where
             }
         }
         // Register element begin events
-        if let Some(list_maps_for_event) = self.event_map.get(&ParserEvent::OnElementBegin) {
-            for event_key in list_maps_for_event.keys() {
-                if self.key_path.match_expr(event_key, false) {
-                    let event_fns = list_maps_for_event.get(event_key).unwrap();
-                    for event_fn in event_fns {
-                        event_fn(None);
-                    }
-                }
-            }
-        }
+        self.trigger_event(ParserEvent::OnElementBegin, None);
         // If string, clear buffer
         match &self.current_status {
             Status::String(_) => {
@@ -143,44 +156,51 @@ where
         &mut self,
         idx: usize,
         parent_status: &Status,
-        mut output_value: Option<Rc<Value>>,
-        buffer_value: Option<Rc<Value>>,
-        move_up_value: Option<Rc<Value>>,
-        new_node_idx: usize // This may be different from idx which represents the object new node is being attached to
-    ) -> Result<Option<String>, ParseError> {
-        // Cannot use self.is_ignoring_current_output() because current_idx is not always the node we are saving
-        let buffer_value = if self.node_map.get(&new_node_idx).map(|node| node.node_ignore_buffer).unwrap_or(false) {
-            None
-        } else {
-            buffer_value.as_ref()
-        };
-        self.on_event_value_completed(buffer_value.map(|val| Rc::clone(&val)));
-        if self.node_map.get(&new_node_idx).map(|node| node.node_ignore_output).unwrap_or(false) {
+        mut output_value: Option<Shared<Value>>,
+        buffer_value: Option<Shared<Value>>,
+        completed_node: &Node, // The node being saved, already removed from the stack. Its index may be different from idx, which represents the node being written to
+        output: &mut String
+    ) {
+        // Only kept when handlers need it, so that the buffer can take the value over instead of copying it
+        let move_up_value = output_value.as_ref().filter(|_| self.has_element_end_handlers()).map(Shared::clone);
+        if completed_node.node_ignore_output {
             output_value = None;
         }
+        // Written before moving up, so that the key path is still the one of the completed value
+        let current_kind = ValueKind::from_status(&self.current_status).unwrap(); // If this panics then it is a logic error
+        self.parser_output.on_status_complete(output, ValueKind::from_status(parent_status), current_kind, idx, &self.key_path, output_value);
+        // Cannot use self.is_ignoring_current_output() because the completed node has already been removed from the stack
+        if !completed_node.node_ignore_buffer {
+            self.on_event_value_completed(buffer_value);
+        }
+        // Basic values nested in a container are written into their parent, so they have no index of their own to complete
+        let has_own_index = match (parent_status, &self.current_status) {
+            (Status::None(_), _) => true,
+            (_, Status::String(_) | Status::Object(_) | Status::Array(_)) => true,
+            _ => false
+        };
+        if has_own_index && !completed_node.node_ignore_output {
+            self.parser_output.on_node_complete(output, current_kind, completed_node.idx, &self.key_path);
+        }
         self.on_event_move_up(move_up_value);
-        Ok(self.parser_output.on_status_complete(parent_status, &self.current_status, idx, output_value))
     }
 
     #[inline]
-    fn on_event_move_up(&mut self, value: Option<Rc<Value>>) {
-        if let Some(list_maps_for_event) = self.event_map.get(&ParserEvent::OnElementEnd) {
-            for event_key in list_maps_for_event.keys() {
-                if self.key_path.match_expr(event_key, false) {
-                    let event_fns = list_maps_for_event.get(event_key).unwrap();
-                    for event_fn in event_fns {
-                        // If string, use the buffer
-                        // Since we don't store previous node data, we can use the buffer to check whether we have been buffering a string
-                        if self.string_value_buffer.len() > 0 {
-                            // No choice but to clone the buffer if we want to support having several references to the same element
-                            let string_value = self.string_value_buffer.clone();
-                            event_fn(Some(Rc::new(Value::String(string_value))));
-                        } else {
-                            event_fn(value.as_ref().map(|v| Rc::clone(&v)));
-                        }
-                    }
-                }
-            }
+    fn has_element_end_handlers(&self) -> bool {
+        self.deferred_events.is_some() || self.event_map.contains_key(&ParserEvent::OnElementEnd)
+    }
+
+    #[inline]
+    fn on_event_move_up(&mut self, value: Option<Shared<Value>>) {
+        if self.has_element_end_handlers() {
+            // If string, use the buffer
+            // Since we don't store previous node data, we can use the buffer to check whether we have been buffering a string
+            let value = if self.string_value_buffer.len() > 0 {
+                Some(Shared::new(Value::String(self.string_value_buffer.clone())))
+            } else {
+                value
+            };
+            self.trigger_event(ParserEvent::OnElementEnd, value);
         }
         self.string_value_buffer.clear();
         self.key_path.move_up();
@@ -189,20 +209,48 @@ where
         }
     }
 
+    /// Calls the handlers of the event attached to the current element, or records the event in a fork
+    #[inline]
+    fn trigger_event(&mut self, event: ParserEvent, value: Option<Shared<Value>>) {
+        match self.deferred_events.as_mut() {
+            Some(deferred_events) => deferred_events.push((event, self.key_path.clone(), value)),
+            None => Self::call_event_handlers(&self.event_map, event, &self.key_path, value),
+        }
+    }
+
+    #[inline]
+    fn call_event_handlers(
+        event_map: &HashMap<ParserEvent, HashMap<String,Vec<F>>>,
+        event: ParserEvent,
+        key_path: &JsonKeyPath,
+        value: Option<Shared<Value>>
+    ) {
+        if let Some(list_maps_for_event) = event_map.get(&event) {
+            for (event_key, event_fns) in list_maps_for_event {
+                if key_path.match_expr(event_key, false) {
+                    for event_fn in event_fns {
+                        event_fn(value.as_ref().map(|v| Shared::clone(&v)));
+                    }
+                }
+            }
+        }
+    }
+
     #[inline]
     // This adds additional optional processing, such as buffering the value
-    fn on_event_value_completed(&mut self, buffer_value: Option<Rc<Value>>) {
+    fn on_event_value_completed(&mut self, buffer_value: Option<Shared<Value>>) {
         if let Some(value_buffer) = self.value_buffer.as_mut() {
             if let Some(output_value) = buffer_value {
-                let output_value_copy = output_value.as_ref().clone();
                 match self.current_status {
-                    Status::String(_) => {
-                        // In case of String, we can't trust output_value, because any potential flushing removes data from it
+                    Status::String(_) if !self.string_value_buffer.is_empty() => {
+                        // In case of String, we can't trust output_value once flushed, because flushing removes data from it
                         // We need to use string_value_buffer in this case
                         (*value_buffer).insert_at_pointer(Value::String(self.string_value_buffer.clone())).unwrap(); // If this panics then it is a logic error
                     },
                     _ => {
-                        (*value_buffer).insert_at_pointer(output_value_copy).unwrap(); // If this panics then it is a logic error
+                        // The output is written by now : unless kept by the parser output or by event handlers, the value is moved instead of copied
+                        let output_value = Shared::try_unwrap(output_value).unwrap_or_else(|output_value| output_value.as_ref().clone());
+                        (*value_buffer).insert_at_pointer(output_value).unwrap(); // If this panics then it is a logic error
                     }
                 }
             }
@@ -213,10 +261,125 @@ where
         self.value_buffer.as_ref().map(|value_buffer| &value_buffer.root)
     }
     
+    /// Copy of the buffered data including the value in progress, as if the document ended now
+    /// Strings contain what has been received so far, numbers drop their trailing incomplete characters,
+    /// and partial literals (or numbers without any digit yet) are null
+    pub fn current_partial_value(&self) -> Option<Value> {
+        let value_buffer = self.value_buffer.as_ref()?;
+        if let Status::None(_) = self.current_status {
+            return None; // Nothing has been parsed yet
+        }
+        let mut value_buffer = value_buffer.clone();
+        if self.is_done || self.is_ignoring_current_buffer() {
+            return Some(value_buffer.root);
+        }
+        let partial_value = match &self.current_status {
+            Status::String(status_string) if !status_string.is_object_key() => {
+                let mut partial_string = self.string_value_buffer.clone();
+                partial_string.push_str(status_string.partial_str());
+                Some(Value::String(partial_string))
+            },
+            Status::Number(status_number) => Some(status_number.partial_value().unwrap_or(Value::Null)),
+            Status::Null(_) | Status::Bool(_) => Some(Value::Null),
+            _ => None // Containers are already in the buffer, and object keys are not written until their value begins
+        };
+        if let Some(partial_value) = partial_value {
+            value_buffer.insert_at_pointer(partial_value).unwrap(); // If this panics then it is a logic error
+        }
+        Some(value_buffer.root)
+    }
+
+    /// Rows reproducing the document written so far, under the indices the next rows will target
+    /// Each open node is assigned its completed values as literals, followed by the reference to its open child
+    /// An open string is assigned its flushed content : the rest comes with the next rows
+    /// Basic values and keys in progress are left out, as the next rows write them whole
+    pub fn resync_rows(&self) -> Option<Vec<ProtocolRow>> {
+        let value_buffer = self.value_buffer.as_ref()?;
+        let mut rows = Vec::new();
+        if self.is_done {
+            // No more rows target the nodes : the whole document can be assigned to the root
+            rows.push(ProtocolRow::assign(self.current_node_idx, value_buffer.root.clone()));
+            return Some(rows);
+        }
+        let mut buffered_value = Some(&value_buffer.root);
+        for (position, node) in self.node_stack.iter().enumerate() {
+            if node.node_ignore_output {
+                break;
+            }
+            let child_node = self.node_stack.get(position + 1);
+            let child_key = match (&node.node_type, child_node) {
+                (NodeType::Object(Some(key)), Some(_)) => Some(ParentNode::Object(key)),
+                (NodeType::Array(arr_idx), Some(_)) => Some(ParentNode::Array(arr_idx - 1)),
+                _ => None // No child, or an object key in progress
+            };
+            let node_value = match (&node.node_type, buffered_value) {
+                (NodeType::Basic, _) => match &self.current_status {
+                    Status::String(status_string) if !status_string.is_object_key() => Value::String(self.string_value_buffer.clone()),
+                    _ => break,
+                },
+                (NodeType::Object(_), Some(Value::Object(map))) => {
+                    let mut map = map.clone();
+                    if let Some(ParentNode::Object(key)) = child_key {
+                        map.shift_remove(key); // Placeholder of the value in progress
+                    }
+                    Value::Object(map)
+                },
+                (NodeType::Object(_), _) => Value::Object(Map::new()),
+                (NodeType::Array(_), Some(Value::Array(values))) => {
+                    let mut values = values.clone();
+                    if let Some(ParentNode::Array(arr_idx)) = child_key {
+                        values.truncate(arr_idx);
+                    }
+                    Value::Array(values)
+                },
+                (NodeType::Array(_), _) => Value::Array(Vec::new()),
+            };
+            rows.push(ProtocolRow::assign(node.idx, node_value));
+
+            let (Some(child_node), Some(child_key)) = (child_node, child_key) else {
+                break;
+            };
+            let child_has_index = match child_node.node_type {
+                NodeType::Basic => matches!(self.current_status, Status::String(_)),
+                _ => true
+            };
+            if child_node.node_ignore_output || !child_has_index {
+                break;
+            }
+            let reference = match child_key {
+                ParentNode::Object(key) => RowValue::KeyReference(key.to_string(), child_node.idx),
+                ParentNode::Array(_) => RowValue::Reference(child_node.idx),
+            };
+            rows.push(ProtocolRow::append(node.idx, reference));
+            buffered_value = match (buffered_value, child_key) {
+                (Some(Value::Object(map)), ParentNode::Object(key)) => map.get(key),
+                (Some(Value::Array(values)), ParentNode::Array(arr_idx)) => values.get(arr_idx),
+                _ => None
+            };
+        }
+        Some(rows)
+    }
+
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    pub fn take_warnings(&mut self) -> Vec<ParseWarning> {
+        std::mem::take(&mut self.warnings)
+    }
+
     pub fn take_buffered_data(&mut self) -> Option<Value> {
         self.value_buffer.as_mut().map(|value_buffer| value_buffer.take_buffered_data())
     }
 
+    pub fn parser_output(&self) -> &O {
+        &self.parser_output
+    }
+
+    pub fn parser_output_mut(&mut self) -> &mut O {
+        &mut self.parser_output
+    }
+
     /// Builds a new subnode with all necessary processing
     /// Returns parent_node_idx
     #[inline]
@@ -225,11 +388,11 @@ where
         let parent_node_idx = self.current_node_idx;
         self.current_node_idx = new_node_idx;
         self.current_status = new_status; // Become the new type
-        let (node_ignore_output, node_ignore_buffer) = self.node_map
-            .get(&parent_node_idx)
+        let (node_ignore_output, node_ignore_buffer) = self.node_stack
+            .last()
             .map(|n| (n.node_ignore_output, n.node_ignore_buffer)).unwrap_or((false, false)); // Init to parent's value
-        self.node_map.insert(self.current_node_idx, Node::new(
-            Some(parent_node_idx),
+        self.node_stack.push(Node::new(
+            new_node_idx,
             node_type,
             node_ignore_output,
             node_ignore_buffer
@@ -237,18 +400,164 @@ where
         parent_node_idx
     }
 
+    /// Processes one byte, writing any row that becomes ready into output
+    #[inline]
+    pub(crate) fn add_char(&mut self, c: &u8, output: &mut String) -> Result<(), ParseError> {
+        let result = if self.skipping.is_some() {
+            self.skip_char(c, output);
+            Ok(())
+        } else {
+            self.process_char(c, output)
+        };
+        let result = match result {
+            Err(err) if self.parser_options.recovery => {
+                self.recover(c, err, output);
+                Ok(())
+            },
+            result => result
+        };
+        self.char_pos += 1;
+        if let Err(err) = result {
+            if !self.has_failed {
+                self.has_failed = true;
+                self.parser_output.on_error(output, &err.msg);
+            }
+            return Err(err);
+        }
+        self.auto_flush(output);
+        Ok(())
+    }
+
+    #[inline]
+    fn add_warning(&mut self, msg: impl Into<String>) {
+        let warning = ParseWarning {
+            position: self.char_pos,
+            msg: msg.into()
+        };
+        log::warn!("JSON recovered at position {} : {}", warning.position, warning.msg);
+        self.warnings.push(warning);
+    }
+
+    /// Recovery mode : handles an error raised by the character c, so that parsing can continue
+    fn recover(&mut self, c: &u8, err: ParseError, output: &mut String) {
+        match &mut self.current_status {
+            Status::String(status_string) if status_string.is_escaping() => {
+                let reprocess = status_string.replace_invalid_escape(c);
+                self.add_warning(format!("{} : replaced with U+FFFD", err.msg));
+                if reprocess {
+                    if let Err(err) = self.process_char(c, output) {
+                        self.recover(c, err, output);
+                    }
+                }
+            },
+            Status::String(status_string) if *c == b'"' => {
+                if status_string.replace_invalid_utf8(true) {
+                    self.add_warning(format!("{} : replaced with U+FFFD", err.msg));
+                    // The string is valid now : it can be ended
+                    if let Err(err) = self.process_char(c, output) {
+                        self.start_skip(c, err, output);
+                    }
+                } else {
+                    self.start_skip(c, err, output);
+                }
+            },
+            Status::None(_) => {
+                // Anything before the root value (ex: text around the JSON) is ignored, with a single warning for consecutive characters
+                if self.last_ignored_pos.map(|pos| pos + 1) != Some(self.char_pos) {
+                    self.add_warning(format!("{} : ignored", err.msg));
+                }
+                self.last_ignored_pos = Some(self.char_pos);
+            },
+            _ => self.start_skip(c, err, output),
+        }
+    }
+
+    /// Recovery mode : abandons the element in progress, and skips up to the next delimiter at the same depth
+    /// The character that raised the error is the first one skipped, as it might be that delimiter
+    fn start_skip(&mut self, c: &u8, err: ParseError, output: &mut String) {
+        self.add_warning(format!("{} : skipping malformed element", err.msg));
+        if let Some(NodeType::Basic) = self.node_stack.last().map(|node| &node.node_type) {
+            // A basic value is in progress : it has not been written yet, so it can be abandoned
+            let abandoned_node = self.node_stack.pop().unwrap();
+            let Some(parent_node) = self.node_stack.last_mut() else {
+                // Malformed root value : there is nothing to resume into
+                self.is_done = true;
+                return;
+            };
+            self.current_node_idx = parent_node.idx;
+            if let NodeType::Array(arr_idx) = &mut parent_node.node_type {
+                *arr_idx = *arr_idx - 1; // The index has not been written yet, give it back
+            }
+            self.string_value_buffer.clear();
+            self.key_path.move_up();
+            if let Some(value_buffer) = self.value_buffer.as_mut() {
+                if !abandoned_node.node_ignore_buffer {
+                    value_buffer.remove_at_pointer();
+                }
+                value_buffer.pointer_up();
+            }
+        }
+        self.skipping = Some(SkipState::default());
+        self.skip_char(c, output);
+    }
+
+    /// Recovery mode : consumes a character of a malformed element
+    fn skip_char(&mut self, c: &u8, output: &mut String) {
+        let skip_state = self.skipping.as_mut().unwrap();
+        if skip_state.in_string {
+            match (skip_state.escaping, c) {
+                (true, _) => skip_state.escaping = false,
+                (false, b'\\') => skip_state.escaping = true,
+                (false, b'"') => skip_state.in_string = false,
+                _ => {}
+            }
+            return;
+        }
+        match c {
+            b'"' => skip_state.in_string = true,
+            b'{' | b'[' => skip_state.depth += 1,
+            b'}' | b']' | b',' if skip_state.depth == 0 => {
+                self.skipping = None;
+                self.resume_after_skip(c, output);
+            },
+            b'}' | b']' => skip_state.depth -= 1,
+            _ => {}
+        }
+    }
+
+    /// Recovery mode : restores the parent container as if its last element was complete, and lets it process the delimiter
+    fn resume_after_skip(&mut self, c: &u8, output: &mut String) {
+        let container_node = self.node_stack.last_mut().unwrap(); // Basic nodes are removed when skipping starts
+        self.current_status = match &mut container_node.node_type {
+            NodeType::Object(potential_key) => {
+                *potential_key = None;
+                Status::Object(StatusObject {
+                    substatus: SubStatusObject::BeforeKV(false)
+                })
+            },
+            NodeType::Array(_) => Status::Array(StatusArray { comma_matched: false }),
+            NodeType::Basic => unreachable!("Logic error : skipping inside a basic value"),
+        };
+        if let Err(err) = self.process_char(c, output) {
+            // The delimiter does not fit the container either : it is skipped as well
+            if self.warnings.last().map(|warning| warning.position) != Some(self.char_pos) {
+                self.add_warning(format!("{} : skipping malformed element", err.msg));
+            }
+            self.skipping = Some(SkipState::default());
+        }
+    }
+
     #[inline]
-    pub(crate) fn add_char(&mut self, c: &u8) -> Result<Option<impl Into<String>>, ParseError> {
+    fn process_char(&mut self, c: &u8, output: &mut String) -> Result<(), ParseError> {
         if self.is_done {
-            return Ok(None);
+            return Ok(());
         }
         let add_char_to_status_result = self.current_status.add_char(c)?;
         if add_char_to_status_result.is_none()  {
             // Current status has absorbed the character and is maintained, no outside status change
-            return Ok(None);
+            return Ok(());
         }
         let (output_value, next_status) = add_char_to_status_result.unwrap();
-        let output_value = output_value.map(|v| Rc::new(v));
 
         // Processing the result of the add_char based on the current status
         match (&mut self.current_status, next_status) {
@@ -265,128 +574,123 @@ where
                     },
                     _ => NodeType::Basic
                 };
-                self.node_map.insert(self.current_node_idx, Node::new(None, new_node_type, false, false));
-                let row = self.parser_output.on_init(
+                self.node_stack.push(Node::new(self.current_node_idx, new_node_type, false, false));
+                self.parser_output.on_init(
+                    output,
                     self.current_node_idx,
-                    next_status.as_ref()
+                    next_status.as_ref().and_then(ValueKind::from_status)
                 );
                 self.current_status = next_status.unwrap(); // StatusNone always returns next status, switch to it whatever it is
                 if let Some(value_buffer) = self.value_buffer.as_mut() {
-                    if let Some(output_value_ref) = output_value.as_ref() {
-                        let output_value_copy = output_value_ref.as_ref().clone();
-                        (*value_buffer).insert_at_pointer(output_value_copy).unwrap(); // Update root, as pointer should have not been moved yet
+                    if let Some(output_value) = output_value {
+                        (*value_buffer).insert_at_pointer(output_value).unwrap(); // Update root, as pointer should have not been moved yet
                     }
                 }
-                return Ok(row);
+                return Ok(());
             },
 
             // A status has been completed
             (current_status, Some(Status::Done(status_done))) => {
-                let current_node = self.node_map.get(&self.current_node_idx);
-                if let Some(Value::String(val)) = output_value.as_deref() {
+                if matches!(current_status, Status::String(status_string) if status_string.is_object_key()) {
+                    self.complete_object_key(output_value, output);
+                    return Ok(());
+                }
+                // The completed node is released here : it is not needed anymore once its value is saved
+                let current_node = self.node_stack.pop();
+                if let Some(Value::String(val)) = output_value.as_ref() {
                     // Push output string into buffer before any potential self.on_event_move_up
-                    self.string_value_buffer.push_str(val);
+                    // Only needed once parts have been flushed : otherwise the output value already is the whole string
+                    if !self.string_value_buffer.is_empty() {
+                        self.string_value_buffer.push_str(val);
+                    }
                 }
+                let output_value = output_value.map(Shared::new);
                 if current_node.is_none() {
                     // Parent object finished
                     self.is_done = true;
                     // self.on_event_move_up(output_value.as_ref()); Needed ?
-                    return Ok(None);
+                    return Ok(());
                 };
                 let current_node = current_node.unwrap();
-                if current_node.parent_idx.is_none() {
+                if self.node_stack.is_empty() {
                     // No parent within node : parsing done, however the output must be appended
                     self.is_done = true;
-                    return self.save_value(
+                    self.save_value(
                         self.current_node_idx,
                         &Status::None(StatusNone::new()),
-                        output_value.as_ref().map(|v| Rc::clone(&v)),
-                        output_value.as_ref().map(|v| Rc::clone(&v)),
+                        output_value.clone(),
                         output_value,
-                        self.current_node_idx,
+                        &current_node,
+                        output
                     );
+                    return Ok(());
                 }
                 let current_idx = self.current_node_idx;
-                let parent_idx = current_node.parent_idx.unwrap();
+
+                // Because the completed node was not the root, parent_node must always be defined
+                let parent_node = self.node_stack.last_mut().unwrap();
+                let parent_idx = parent_node.idx;
                 // Already update current_node_idx cursor back to the parent value.
                 // For clarity, let's not use it afterwards (use parent_idx instead)
                 self.current_node_idx = parent_idx;
 
-                // Because parent_idx exists, parent_node must always be defined
-                let parent_node = self.node_map.get_mut(&parent_idx).unwrap();
                 match &mut parent_node.node_type {
                     node::NodeType::Object(ref mut potential_key) => {
-                        if let Some(key) = potential_key.take() {
+                        if potential_key.take().is_some() {
                             // The key exists => we are returning from the object value
                             let (
                                 save_idx,
                                 save_value_output,
                                 save_value_buffer
-                            ): (usize, Option<Rc<Value>>, Option<Rc<Value>>) = match current_status {
+                            ): (usize, Option<Shared<Value>>, Option<Shared<Value>>) = match current_status {
                                 // The way to write the row, however, depends on the type
-                                // Basic types, we have to append to the parent object itself
+                                // Basic types, we have to append to the parent object itself, under the key
                                 // Except for the value we buffer, in which case it's straightforward
                                 Status::Null(_)|
                                 Status::Bool(_) |
                                 Status::Number(_) => {
-                                    let value = output_value.as_ref().unwrap(); // A basic type, when Done, absolutely returns a value
+                                    let value = output_value.unwrap(); // A basic type, when Done, absolutely returns a value
                                     (
                                         parent_idx,
-                                        Some(Rc::new(json!({key: value}))),
-                                        Some(Rc::clone(&value))
+                                        Some(Shared::clone(&value)),
+                                        Some(value)
                                     )
                                 },
                                 // For strings, we have already initialized it, so append to self
                                 Status::String(_) => {
                                     (
                                         current_idx,
-                                        output_value.as_ref().map(|v| Rc::clone(v)), // Value might not be present if flushed
-                                        Some(output_value.as_ref().map(|v|
-                                            Rc::clone(v))
-                                            .unwrap_or(Rc::new(Value::String(String::new()))
-                                        )), // For the buffer, a String value should always be initialized, at least as empty string
+                                        output_value.clone(), // Value might not be present if flushed
+                                        Some(output_value.unwrap_or_else(|| Shared::new(Value::String(String::new())))) // For the buffer, a String value should always be initialized, at least as empty string
                                     )
                                 },
                                 Status::Object(_) | Status::Array(_) => {
                                     (
                                         0, // irrelevant here
-                                        output_value.as_ref().map(|v| Rc::clone(v)),
+                                        output_value.clone(),
                                         output_value
                                     )
                                 },
                                 _ => unreachable!("All relevant types are covered, aren't they?")
                             };
-                            let saved_value = self.save_value(
+                            self.save_value(
                                 save_idx,
                                 &Status::Object(StatusObject::new()),
-                                save_value_output.as_ref().map(|v| Rc::clone(&v)),
-                                save_value_buffer,
                                 save_value_output,
-                                current_idx
+                                save_value_buffer,
+                                &current_node,
+                                output
                             );
                             self.current_status = Status::Object(StatusObject {
                                 substatus: SubStatusObject::BeforeKV(status_done.comma_matched)
                             });
                             if status_done.done_object {
                                 // Not only the value is completed, but the current object must be too : go back up once again
-                                self.move_up();
+                                self.move_up(output);
                             }
-                            return saved_value;
+                            return Ok(());
                         } else {
-                            match current_status {
-                                Status::String(_) => {
-                                    // Key does not exist yet => we are returning from the String value for the key : save it and continue
-                                    let value = output_value.unwrap(); // String value for the object key must exist
-                                    let new_key = value.as_str().unwrap().to_string();
-                                    let output_on_new_key = self.parser_output.on_object_key_complete(&new_key);
-                                    *potential_key = Some(new_key);
-                                    self.current_status = Status::Object(StatusObject {
-                                        substatus: SubStatusObject::BetweenKV(false)
-                                    });
-                                    return Ok(output_on_new_key);
-                                },
-                                _ => unreachable!("Parser logic error : non-string status without potential key")
-                            };
+                            unreachable!("Parser logic error : value without potential key")
                         }
                     },
                     node::NodeType::Array(_) => {
@@ -394,58 +698,52 @@ where
                             save_idx,
                             output_value,
                             buffer_value
-                        ): (usize, Option<Rc<Value>>, Option<Rc<Value>>) = match current_status {
+                        ): (usize, Option<Shared<Value>>, Option<Shared<Value>>) = match current_status {
                             // The way to write the row, however, depends on the type
                             // Basic types, we have to append to the parent object itself
                             Status::Null(_)|
                             Status::Bool(_) |
                             Status::Number(_) => {
-                                let value = output_value.as_ref().unwrap();
+                                let value = output_value.unwrap();
                                 (
                                     parent_idx,
-                                    Some(Rc::clone(&value)),
-                                    Some(Rc::clone(&value))
+                                    Some(Shared::clone(&value)),
+                                    Some(value)
                                 )
                             },
                             Status::String(_) => {
                                 (
                                     current_idx,
                                     // For output, it has already been initialized, so we should not add output if empty
-                                    output_value.as_ref().and_then(|v| {
-                                        if v.as_str().unwrap().len() > 0 {
-                                            Some(Rc::clone(&v))
-                                        } else {
-                                            None
-                                        }
-                                    }),
+                                    output_value.as_ref().filter(|v| !v.as_str().unwrap().is_empty()).map(Shared::clone),
                                     // For buffer, however, we never want None, as it will buffer the empty string as null
-                                    Some(output_value.unwrap_or(Rc::new(Value::String(String::new()))))
+                                    Some(output_value.unwrap_or_else(|| Shared::new(Value::String(String::new()))))
                                 )
                             },
                             Status::Object(_) | Status::Array(_) => {
                                 (
                                     0, // irrelevant here
-                                    output_value.as_ref().map(|v| Rc::clone(v)),
+                                    output_value.clone(),
                                     output_value
                                 )
                             },
                             _ => unreachable!("All base types are covered, aren't they?")
                         };
-                        let saved_value = self.save_value(
+                        self.save_value(
                             save_idx,
                             &Status::Array(StatusArray::new()),
-                            output_value.as_ref().map(|v| Rc::clone(&v)),
-                            buffer_value,
                             output_value,
-                            current_idx
+                            buffer_value,
+                            &current_node,
+                            output
                         );
                         self.current_status = Status::Array(StatusArray { comma_matched: status_done.comma_matched });
                         if status_done.done_array {
                             // Not only the value is completed, but the current array must be too
                             // => go back up once again
-                            self.move_up();
+                            self.move_up(output);
                         }
-                        return saved_value;
+                        return Ok(());
                     },
                     node::NodeType::Basic => {
                         unreachable!("Nested data cannot return into non-object or non-array")
@@ -473,44 +771,48 @@ where
                     },
                     _ => unreachable!("Invalid flow")
                 };
-                let parent_node = self.node_map.get_mut(&parent_node_idx).unwrap();
+                // The new node is on top of the stack, right above its parent
+                let parent_node_position = self.node_stack.len() - 2;
+                let parent_node = &mut self.node_stack[parent_node_position];
 
                 // Some datas change depending on whether we are making a subnode of object or array
                 // Parametrize below
-                let key_and_row: Option<(String, ParentNode)> = match &mut parent_node.node_type {
-                    NodeType::Object(ref potential_key) => { // Only when we are parsing the string that is the value of the object (because key is existing)
-                        if let Some(key) = potential_key {
-                            let key_copy = key.clone();
-                            Some((key.clone(), ParentNode::Object(key_copy)))
-                        } else {
-                            // This String is being used to parse an object's key : do not write now, wait for the value
-                            None
-                        }
+                let key_and_array_idx: Option<(String, Option<usize>)> = match &mut parent_node.node_type {
+                    NodeType::Object(potential_key) => { // Only when we are parsing the string that is the value of the object (because key is existing)
+                        // The key is borrowed from the parent while writing, and given back below
+                        // None when this String is being used to parse an object's key : do not write now, wait for the value
+                        potential_key.take().map(|key| (key, None))
                     }
                     NodeType::Array(arr_idx) => { // Or when its the value of the array
                         let arr_idx_copy = *arr_idx;
                         *arr_idx = *arr_idx + 1; // Advance index after copy
-                        Some((arr_idx_copy.to_string(), ParentNode::Array(arr_idx_copy)))
+                        Some((arr_idx_copy.to_string(), Some(arr_idx_copy)))
                     },
                     _ => unreachable!("Logic error : String cannot be a child of non-object and non-array")
                 };
 
                 // Write data
-                if let Some((key, parent_node)) = key_and_row {  
+                if let Some((key, array_idx)) = key_and_array_idx {
                     self.on_event_move_down(&key);
                     if !self.is_ignoring_current_output() {
-                        Ok(self.parser_output.on_new_subnode(
+                        let parent_node = match array_idx {
+                            Some(array_idx) => ParentNode::Array(array_idx),
+                            None => ParentNode::Object(&key),
+                        };
+                        self.parser_output.on_new_subnode(
+                            output,
                             parent_node,
-                            &self.current_status,
+                            ValueKind::from_status(&self.current_status).unwrap(), // If this panics then it is a logic error
                             parent_node_idx,
-                            self.current_node_idx
-                        ))
-                    } else {
-                        Ok(None)
+                            self.current_node_idx,
+                            &self.key_path
+                        );
+                    }
+                    if array_idx.is_none() {
+                        self.node_stack[parent_node_position].node_type = NodeType::Object(Some(key));
                     }
-                } else {
-                    Ok(None)
                 }
+                Ok(())
             },
 
             (cur_status, next_status) => {
@@ -521,56 +823,166 @@ where
         }
     }
 
+    /// Returning from the String value for an object key : save it as the potential key of the parent object and continue
+    /// The key is moved out of the status output, without any copy
+    #[inline]
+    fn complete_object_key(&mut self, output_value: Option<Value>, output: &mut String) {
+        self.node_stack.pop(); // Keys are not written as nodes of their own
+        let new_key = match output_value {
+            Some(Value::String(key)) => key,
+            _ => String::new(), // Empty key
+        };
+        self.parser_output.on_object_key_complete(output, &new_key, &self.key_path);
+        let parent_node = self.node_stack.last_mut().unwrap(); // An object key always has a parent object
+        self.current_node_idx = parent_node.idx;
+        parent_node.node_type = NodeType::Object(Some(new_key));
+        self.current_status = Status::Object(StatusObject {
+            substatus: SubStatusObject::BetweenKV(false)
+        });
+    }
+
+    /// Processes a slice of bytes, pushing every output row into output
+    /// Equivalent to calling add_char for each byte, except that string contents are scanned in bulk
+    pub(crate) fn feed(&mut self, bytes: &[u8], output: &mut String) -> Result<(), (usize, ParseError)> {
+        let mut position = 0;
+        while position < bytes.len() {
+            if let Status::String(status_string) = &self.current_status {
+                if !self.is_done && self.skipping.is_none() {
+                    let run_end = position + self.plain_run_limit(status_string, &bytes[position..]);
+                    if let Status::String(status_string) = &mut self.current_status {
+                        let run_len = status_string.add_plain_run(&bytes[position..run_end]);
+                        position += run_len;
+                        self.char_pos += run_len;
+                    }
+                    self.auto_flush(output);
+                    if position == bytes.len() {
+                        break;
+                    }
+                }
+            }
+            if let Err(err) = self.add_char(&bytes[position], output) {
+                return Err((position, err));
+            }
+            position += 1;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn flush(&mut self, output: &mut String) {
+        if self.parser_options.recovery {
+            if let Status::String(status_string) = &mut self.current_status {
+                if status_string.replace_invalid_utf8(false) {
+                    self.add_warning("String is not in UTF8 : replaced with U+FFFD");
+                }
+            }
+        }
+        let flushed = match &mut self.current_status {
+            Status::String(status_string) if self.parser_options.grapheme_safe_flush => status_string.flush_grapheme_safe(),
+            current_status => current_status.flush(),
+        };
+        self.pending_flush_since = None;
+        if let Some(data) = flushed {
+            match &data {
+                Value::String(str) => {
+                    // Save in buffer
+                    self.string_value_buffer.push_str(str);
+                },
+                _ => {}
+            }
+            self.parser_output.on_flush(output, self.current_node_idx, &self.key_path, &data);
+        }
+    }
+
+    /// Flushes the string in progress if the flush policy requires it, without adding any byte
+    /// Lets FlushPolicy::Interval write bytes that have been waiting for too long while no input arrives
+    #[inline]
+    pub fn poll_flush(&mut self, output: &mut String) {
+        self.auto_flush(output);
+    }
+
+    /// Maximum number of bytes that can be absorbed in bulk by a string without missing an automatic flush
+    /// Runs are cut right after the bytes that may trigger a flush, so that feed() flushes exactly like add_char()
+    #[inline]
+    fn plain_run_limit(&self, status_string: &StatusString, bytes: &[u8]) -> usize {
+        match &self.parser_options.flush_policy {
+            FlushPolicy::MaxBufferedBytes(max_bytes) => {
+                max_bytes.saturating_sub(status_string.buffered_len()).max(1).min(bytes.len())
+            },
+            FlushPolicy::WordBoundary => {
+                bytes.iter().position(|c| c.is_ascii_whitespace()).map(|pos| pos + 1).unwrap_or(bytes.len())
+            },
+            FlushPolicy::SentenceBoundary => {
+                // Whitespaces, as well as the last byte of CJK full stops (。！？)
+                bytes.iter().position(|c| c.is_ascii_whitespace() || matches!(c, 0x81 | 0x82 | 0x9F)).map(|pos| pos + 1).unwrap_or(bytes.len())
+            },
+            FlushPolicy::Manual | FlushPolicy::Interval { .. } => bytes.len(),
+        }
+    }
+
+    /// Flushes the string in progress if the flush policy requires it
     #[inline]
-    pub fn flush(&mut self) -> Option<String> {
-        match self.current_status.flush() {
-            Some(data) => {
-                match &data {
-                    Value::String(str) => {
-                        // Save in buffer
-                        self.string_value_buffer.push_str(str);
+    fn auto_flush(&mut self, output: &mut String) {
+        let should_flush = match (&self.current_status, &self.parser_options.flush_policy) {
+            (_, FlushPolicy::Manual) => return,
+            (Status::String(status_string), _) if status_string.buffered_len() > 0 => {
+                match &self.parser_options.flush_policy {
+                    FlushPolicy::MaxBufferedBytes(max_bytes) => status_string.buffered_len() >= *max_bytes,
+                    FlushPolicy::WordBoundary => status_string.ends_with_word_boundary(),
+                    FlushPolicy::SentenceBoundary => status_string.ends_with_sentence_boundary(),
+                    FlushPolicy::Interval { interval, clock } => {
+                        let now = clock.now();
+                        let pending_since = *self.pending_flush_since.get_or_insert(now);
+                        now.saturating_sub(pending_since) >= *interval
                     },
-                    _ => {}
+                    FlushPolicy::Manual => unreachable!(),
                 }
-                self.parser_output.on_flush(self.current_node_idx, &data)
+            },
+            _ => {
+                // Nothing to flush : either not in a string, or everything has already been written
+                self.pending_flush_since = None;
+                false
             }
-            None => None,
+        };
+        if should_flush {
+            self.flush(output);
         }
     }
 
     // Silently move up the node map
     #[inline]
-    pub fn move_up(&mut self) {
-        let current_node = self.node_map.get(&self.current_node_idx);
+    pub fn move_up(&mut self, output: &mut String) {
+        let current_node = self.node_stack.pop();
+        if let Some(node) = current_node.as_ref().filter(|node| !node.node_ignore_output) {
+            // Only containers are closed this way, when their last value was a number
+            let completed_kind = match node.node_type {
+                NodeType::Object(_) => ValueKind::Object,
+                _ => ValueKind::Array,
+            };
+            self.parser_output.on_node_complete(output, completed_kind, node.idx, &self.key_path);
+        }
         if current_node.is_none() {
             // Current object is top level
             self.is_done = true;
         } else {
-            let current_node = current_node.unwrap();
-            if current_node.parent_idx.is_none() {
+            let parent_node = self.node_stack.last_mut();
+            if parent_node.is_none() {
                 // No parent within node : parsing done
                 self.is_done = true;
             } else {
-                let parent_idx = current_node.parent_idx.unwrap();
-                self.current_node_idx = parent_idx;
-                let parent_node = self.node_map.get_mut(&parent_idx);
-                if parent_node.is_none() {
-                    // I dont see a usecase for this, but just in case (instead of unwrap)
-                    self.is_done = true;
-                } else {
-                    let parent_node = parent_node.unwrap();
-                    match &mut parent_node.node_type {
-                        NodeType::Object(potential_key) => {
-                            *potential_key = None; // When arriving back up in an object, make sure to unset the key (string key case is handled elsewhere)
-                            self.current_status = Status::Object(StatusObject {
-                                substatus: SubStatusObject::BeforeKV(false)
-                            });
-                        },
-                        NodeType::Array(_1) => {
-                            self.current_status = Status::Array(StatusArray { comma_matched: false }); 
-                        },
-                        NodeType::Basic => unreachable!("Nested data cannot return into non-object or non-array"),
-                    }
+                let parent_node = parent_node.unwrap();
+                self.current_node_idx = parent_node.idx;
+                match &mut parent_node.node_type {
+                    NodeType::Object(potential_key) => {
+                        *potential_key = None; // When arriving back up in an object, make sure to unset the key (string key case is handled elsewhere)
+                        self.current_status = Status::Object(StatusObject {
+                            substatus: SubStatusObject::BeforeKV(false)
+                        });
+                    },
+                    NodeType::Array(_1) => {
+                        self.current_status = Status::Array(StatusArray { comma_matched: false }); 
+                    },
+                    NodeType::Basic => unreachable!("Nested data cannot return into non-object or non-array"),
                 }
             }
         }
@@ -592,21 +1004,160 @@ where
         event_list.push(func);
     }
 
+    pub fn get_options(&self) -> &ParserOptions {
+        &self.parser_options
+    }
+
     pub fn set_options(&mut self, parser_options: ParserOptions) {
         self.parser_options = parser_options;
     }
 
+    /// Copies the whole parsing state into an independent mapper, with its own forked index generator
+    /// Event handlers cannot be cloned : the fork records the events instead, and commit() calls the handlers of self
+    pub fn fork(&self) -> Self
+    where
+        O: Clone
+    {
+        Self {
+            key_path: self.key_path.clone(),
+            ref_index_generator: self.ref_index_generator.fork(),
+            node_stack: self.node_stack.clone(),
+            current_node_idx: self.current_node_idx,
+            current_status: self.current_status.clone(),
+            event_map: HashMap::new(),
+            deferred_events: if self.event_map.is_empty() && self.deferred_events.is_none() { None } else { Some(Vec::new()) },
+            is_done: self.is_done,
+            string_value_buffer: self.string_value_buffer.clone(),
+            value_buffer: self.value_buffer.clone(),
+            parser_options: self.parser_options.clone(),
+            parser_output: self.parser_output.clone(),
+            pending_flush_since: self.pending_flush_since,
+            char_pos: self.char_pos,
+            skipping: self.skipping.clone(),
+            last_ignored_pos: self.last_ignored_pos,
+            warnings: self.warnings.clone(),
+            has_failed: self.has_failed,
+        }
+    }
+
+    /// Takes over the parsing state of a fork, keeping the event handlers of self, which handle the events recorded by the fork
+    /// The shared index generator is advanced past any index generated by the fork
+    /// Fails without any change if the fork and the shared index generator have both generated indices since the fork
+    pub fn commit(&mut self, forked: Self) -> Result<(), ParseError> {
+        self.ref_index_generator.commit(&forked.ref_index_generator)?;
+        for (event, key_path, value) in forked.deferred_events.into_iter().flatten() {
+            match self.deferred_events.as_mut() {
+                Some(deferred_events) => deferred_events.push((event, key_path, value)), // Self is a fork too
+                None => Self::call_event_handlers(&self.event_map, event, &key_path, value),
+            }
+        }
+        self.key_path = forked.key_path;
+        self.node_stack = forked.node_stack;
+        self.current_node_idx = forked.current_node_idx;
+        self.current_status = forked.current_status;
+        self.is_done = forked.is_done;
+        self.string_value_buffer = forked.string_value_buffer;
+        self.value_buffer = forked.value_buffer;
+        self.parser_options = forked.parser_options;
+        self.parser_output = forked.parser_output;
+        self.pending_flush_since = forked.pending_flush_since;
+        self.char_pos = forked.char_pos;
+        self.skipping = forked.skipping;
+        self.last_ignored_pos = forked.last_ignored_pos;
+        self.warnings = forked.warnings;
+        self.has_failed = forked.has_failed;
+        Ok(())
+    }
+
     /// Call this method when all data has been sent. There might be lingering state
-    pub fn finish(&mut self) {
-        match self.current_status.finish() {
-            Ok(final_value) => {
-                if let Some(final_value) = final_value {
-                    self.on_event_value_completed(Some(Rc::new(final_value)));
+    /// Returns an error listing the incomplete elements if the document has been cut before its end
+    /// Writes the rows of a root number, then either the end of the stream or the error
+    pub fn finish(&mut self, output: &mut String) -> Result<(), FinishError> {
+        let result = self.finish_document(output);
+        if !self.has_failed {
+            match &result {
+                Ok(()) => self.parser_output.on_finish(output),
+                Err(err) => self.parser_output.on_error(output, &err.to_string()),
+            }
+        }
+        result
+    }
+
+    fn finish_document(&mut self, output: &mut String) -> Result<(), FinishError> {
+        if self.is_done {
+            return Ok(());
+        }
+        let mut truncations = Vec::new();
+        let current_key_path = self.key_path.get_current_key().to_string();
+        let mut is_object_key = false;
+        match &mut self.current_status {
+            Status::None(_) => {
+                truncations.push(Truncation::EmptyDocument);
+            },
+            Status::Number(status_number) => {
+                // A number only knows it is complete when the next character arrives : the end of the document is one
+                match status_number.finish() {
+                    Ok(final_value) => {
+                        if let Some(final_value) = final_value {
+                            let final_value = Shared::new(final_value);
+                            if let [root_node] = self.node_stack.as_slice() {
+                                // A root number is only written now, as nothing follows it
+                                if !root_node.node_ignore_output {
+                                    let root_idx = root_node.idx;
+                                    self.parser_output.on_status_complete(output, None, ValueKind::Number, root_idx, &self.key_path, Some(Shared::clone(&final_value)));
+                                    self.parser_output.on_node_complete(output, ValueKind::Number, root_idx, &self.key_path);
+                                }
+                            }
+                            self.on_event_value_completed(Some(final_value));
+                        }
+                    },
+                    Err(err) => {
+                        truncations.push(Truncation::InvalidNumber { key_path: current_key_path, msg: err.msg });
+                    },
                 }
             },
-            Err(err) => {
-                log::error!("{}", err.to_string())
+            Status::Null(_) | Status::Bool(_) => {
+                truncations.push(Truncation::UnterminatedLiteral { key_path: current_key_path });
+            },
+            Status::String(status_string) => {
+                is_object_key = status_string.is_object_key();
+                truncations.push(Truncation::UnterminatedString { key_path: current_key_path, object_key: is_object_key });
             },
+            Status::Object(_) | Status::Array(_) => {}, // Reported with the open nodes below
+            Status::Done(_) => unreachable!("Done status must not be final"),
+        }
+
+        // Open containers, from the innermost one
+        let mut key_path = self.key_path.clone();
+        let top_position = self.node_stack.len().saturating_sub(1);
+        for (position, node) in self.node_stack.iter().enumerate().rev() {
+            match &node.node_type {
+                NodeType::Object(potential_key) => {
+                    if let (Some(key), Status::Object(_)) = (potential_key, &self.current_status) {
+                        if position == top_position {
+                            truncations.push(Truncation::DanglingKey {
+                                key_path: key_path.get_current_key().to_string(),
+                                key: key.clone()
+                            });
+                        }
+                    }
+                    truncations.push(Truncation::OpenObject { key_path: key_path.get_current_key().to_string() });
+                },
+                NodeType::Array(_) => {
+                    truncations.push(Truncation::OpenArray { key_path: key_path.get_current_key().to_string() });
+                },
+                NodeType::Basic => {}, // The value in progress, already reported from its status
+            }
+            // Every node except the root moved down the key path once, except object keys
+            if position > 0 && !(position == top_position && is_object_key) {
+                key_path.move_up();
+            }
+        }
+
+        if truncations.is_empty() {
+            Ok(())
+        } else {
+            Err(FinishError { truncations })
         }
     }
 }
\ No newline at end of file
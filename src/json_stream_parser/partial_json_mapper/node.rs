@@ -1,15 +1,16 @@
 /// Type of node, especially needed when going back up and checking where we're at. Parametrization included
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) enum NodeType {
     Object(Option<String>), // The parameter is a potential key. If exists, then when returning from a subobject, we know that we are dealing with a string key
     Array(usize), // Counting indices
     Basic // A basic type for non-containers
 }
 
-/// Structure holding information about the node being written, or previously written
-#[derive(Debug)]
+/// Structure holding information about a node being written
+/// Only open nodes are kept (see node_stack of PartialJsonMapper) : the parent of a node is the one below it in the stack
+#[derive(Debug, Clone)]
 pub(crate) struct Node {
-    pub parent_idx: Option<usize>, // Pointing the parent node if any. Root does not have a parent node
+    pub idx: usize, // Reference index of this node in the protocol
     pub node_type: NodeType,
     pub node_ignore_output: bool, // Registering within the node whether this node is ignoring outputting data being parsed
     pub node_ignore_buffer: bool, // Registering within the node whether this node is ignoring buffering data being parsed
@@ -17,16 +18,16 @@ pub(crate) struct Node {
 
 impl Node {
     pub fn new(
-        parent_idx: Option<usize>,
+        idx: usize,
         node_type: NodeType,
         node_ignore_output: bool,
         node_ignore_buffer: bool
     ) -> Self {
         Self {
-            parent_idx,
+            idx,
             node_type,
             node_ignore_output,
             node_ignore_buffer
         }
     }
-}
\ No newline at end of file
+}
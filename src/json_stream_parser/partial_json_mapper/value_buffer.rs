@@ -2,10 +2,13 @@ use serde_json::Value;
 
 /// Helper struct which builds a Value while going through partial JSON
 /// It is used optionally if enable_buffering flag is set
+#[derive(Clone)]
 pub(crate) struct ValueBuffer {
     pub(crate) root: Value,
-    // serde_json::Value pointer as per https://docs.rs/serde_json/latest/serde_json/value/enum.Value.html#method.pointer
+    // Keys from the root to the current value, each preceded by a slash (ex: "/list/0/name")
+    // Keys are kept as is, without the escaping of JSON pointers : segments are delimited by segment_starts instead
     pub(crate) pointer: String,
+    segment_starts: Vec<usize>, // Position of each key in pointer
 }
 
 impl ValueBuffer {
@@ -13,6 +16,7 @@ impl ValueBuffer {
         Self {
             root,
             pointer: Self::pointer_init(),
+            segment_starts: Vec::new(),
         }
     }
 
@@ -25,21 +29,45 @@ impl ValueBuffer {
         let mut root_value = Value::Null;
         std::mem::swap(&mut root_value, &mut self.root);
         self.pointer = Self::pointer_init();
+        self.segment_starts.clear();
         return root_value;
     }
 
     pub fn pointer_up(&mut self) {
-        if let Some(last_slash_pos) = self.pointer.rfind('/') {
-            self.pointer.replace_range(last_slash_pos.., "");
+        if let Some(segment_start) = self.segment_starts.pop() {
+            self.pointer.truncate(segment_start - 1); // Also removing the slash
         }
     }
 
+    /// Value at the given number of levels below the root, following the pointer
+    /// Walking the tree directly avoids the parsing and the allocations of Value::pointer_mut
+    fn value_at_depth(&mut self, depth: usize) -> Option<&mut Value> {
+        let mut value = &mut self.root;
+        for (position, segment_start) in self.segment_starts[..depth].iter().enumerate() {
+            let segment_end = self.segment_starts.get(position + 1).map(|next_start| next_start - 1).unwrap_or(self.pointer.len());
+            let key = &self.pointer[*segment_start..segment_end];
+            value = match value {
+                // The value being built is almost always the last one of its parent, which is checked before looking the key up
+                Value::Object(map) => {
+                    if map.keys().next_back().is_some_and(|last_key| last_key == key) {
+                        map.values_mut().next_back()?
+                    } else {
+                        map.get_mut(key)?
+                    }
+                },
+                Value::Array(arr) => arr.get_mut(key.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
     // Not only moves pointer down, but optionally also inserts null value there - as it is expected for it to be replaced
     // Not inserting the placeholder is useful when we are filtering the values, keeping the pointer as-is but without extra insertion
     pub fn pointer_down(&mut self, key: &str, insert_placeholder: bool) -> Result<(), String> {
         // Validation that going down is applicable
         if insert_placeholder {
-            match self.root.pointer_mut(&self.pointer) {
+            match self.value_at_depth(self.segment_starts.len()) {
                 Some(current_value) => {
                     match current_value {
                         Value::Null |
@@ -73,12 +101,30 @@ impl ValueBuffer {
             }
         }
         // Update pointer
-        self.pointer = format!("{}/{key}", self.pointer);
+        self.pointer.push('/');
+        self.segment_starts.push(self.pointer.len());
+        self.pointer.push_str(key);
         Ok(())
     }
 
+    /// Removes the value at the pointer from its parent, without moving the pointer
+    /// The value must be the last one of its parent, as when abandoning the value being parsed
+    pub fn remove_at_pointer(&mut self) {
+        let Some(segment_start) = self.segment_starts.last().copied() else { return };
+        let key = self.pointer[segment_start..].to_string();
+        match self.value_at_depth(self.segment_starts.len() - 1) {
+            Some(Value::Object(map)) => {
+                map.shift_remove(&key);
+            },
+            Some(Value::Array(arr)) => {
+                arr.pop();
+            },
+            _ => {}
+        }
+    }
+
     pub fn insert_at_pointer(&mut self, value: Value) -> Result<(), String> {
-        match self.root.pointer_mut(&self.pointer) {
+        match self.value_at_depth(self.segment_starts.len()) {
             Some(existing_value) => {
                 *existing_value = value;
                 Ok(())
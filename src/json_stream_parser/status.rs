@@ -19,7 +19,7 @@ pub mod status_object;
 pub mod status_string;
 pub mod status_done;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Status {
     None(StatusNone), // Typically at the very first iteration, when the root object is still undefined
     Null(StatusNull),
@@ -4,7 +4,7 @@ use crate::json_stream_parser::error::ParseError;
 
 use super::{Status, StatusBool, StatusDone, StatusNull, StatusNumber, StatusObject, StatusString, StatusTrait};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct StatusArray {
     pub comma_matched: bool,
 }
@@ -5,7 +5,7 @@ use crate::json_stream_parser::error::ParseError;
 use super::{Status, StatusDone, StatusTrait};
 
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct StatusBool {
     match_so_far: Vec<u8> // Contains incomplete sequence
 }
@@ -1,5 +1,5 @@
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 /// Struct needed to store a couple of params along with being Done parsing the subitem
 pub struct StatusDone {
     pub done_object: bool, // True if done on detecting '}' as inner value stop condition (currently only needed for Number) to double up
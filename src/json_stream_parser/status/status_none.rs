@@ -4,7 +4,7 @@ use crate::json_stream_parser::error::ParseError;
 
 use super::{Status, StatusArray, StatusBool, StatusNull, StatusNumber, StatusObject, StatusString, StatusTrait};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct StatusNone {
     
 }
@@ -4,12 +4,24 @@ use crate::json_stream_parser::error::ParseError;
 
 use super::{Status, StatusTrait};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct StatusNumber {
     dot_matched: bool,
     match_so_far: Vec<u8> // Contains incomplete sequence
 }
 
+impl StatusNumber {
+    /// Best-effort value of the incomplete number : trailing characters that cannot end a number (. e E + -) are dropped
+    /// Returns None if there is still no valid number, for ex when only a minus sign has been read
+    pub fn partial_value(&self) -> Option<Value> {
+        let mut match_so_far = self.match_so_far.as_slice();
+        while let [rest @ .., b'.' | b'e' | b'E' | b'+' | b'-'] = match_so_far {
+            match_so_far = rest;
+        }
+        serde_json::from_slice::<Number>(match_so_far).ok().map(Value::Number)
+    }
+}
+
 impl StatusTrait for StatusNumber {
     fn new() -> Self {
         Self {
@@ -26,9 +38,9 @@ impl StatusTrait for StatusNumber {
                 return Ok(None);
             }
             _ => {
-                let mut out_vec = Vec::<u8>::new(); // To be swapped with current data, since that one is no longer needed after this return
-                std::mem::swap(&mut self.match_so_far, &mut out_vec);
-                match serde_json::from_slice::<Number>(&out_vec) {
+                let parsed = serde_json::from_slice::<Number>(&self.match_so_far);
+                self.match_so_far.clear(); // Not needed anymore after this return
+                match parsed {
                     Ok(serde_number) => {
                         // Number is a bit special base type in that we don't know whether we are done parsing until we hit an invalid character
                         // This makes it that we might not only be done with the number itself, but also with the parent object or array
@@ -80,7 +92,7 @@ impl StatusTrait for StatusNumber {
                 .map_err(|_|
                     ParseError::new(
                         format!("Error parsing serde number on finish : {}",
-                            String::from_utf8_lossy(&self.match_so_far)
+                            String::from_utf8_lossy(&out_vec)
                         )
                     )
                 )
@@ -4,13 +4,13 @@ use crate::json_stream_parser::error::ParseError;
 
 use super::{Status, StatusArray, StatusBool, StatusNull, StatusNumber, StatusString, StatusTrait};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum SubStatusObject {
     BeforeKV(bool), // true if ',' is matched
     BetweenKV(bool), // true if ':' is matched
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct StatusObject {
     pub substatus: SubStatusObject
 }
@@ -1,17 +1,18 @@
 use serde_json::Value;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::json_stream_parser::error::ParseError;
 
 use super::{Status, StatusTrait};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct StatusString {
     string_in_progress: Vec<u8>,
     escape: EscapeState,
     is_object_key: bool,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum EscapeState {
     None, // Not escaping
     Began, // After \ is detected
@@ -23,6 +24,109 @@ impl StatusString {
         self.is_object_key = true;
         self
     }
+
+    /// Fast path absorbing a whole run of regular characters at once, up to the next '"' or '\\'
+    /// Returns the number of bytes absorbed, which are the same as the ones add_char would have absorbed one by one
+    /// Nothing is absorbed while an escape sequence is in progress, as it needs to be handled by add_char
+    #[inline]
+    pub fn add_plain_run(&mut self, bytes: &[u8]) -> usize {
+        if self.escape != EscapeState::None {
+            return 0;
+        }
+        let run_len = memchr::memchr2(b'"', b'\\', bytes).unwrap_or(bytes.len());
+        self.string_in_progress.extend_from_slice(&bytes[..run_len]);
+        run_len
+    }
+
+    #[inline]
+    pub fn is_object_key(&self) -> bool {
+        self.is_object_key
+    }
+
+    #[inline]
+    pub fn is_escaping(&self) -> bool {
+        self.escape != EscapeState::None
+    }
+
+    /// Recovery for an invalid escape sequence, which is replaced with U+FFFD
+    /// Returns true if the character that made the sequence invalid is not part of it, and must be processed again
+    pub fn replace_invalid_escape(&mut self, c: &u8) -> bool {
+        let reprocess = matches!(self.escape, EscapeState::UTF8(_)) && !c.is_ascii_hexdigit();
+        self.string_in_progress.extend_from_slice(char::REPLACEMENT_CHARACTER.to_string().as_bytes());
+        self.escape = EscapeState::None;
+        reprocess
+    }
+
+    /// Recovery for invalid UTF-8 : invalid sequences are replaced with U+FFFD
+    /// Unless the string is complete, a trailing incomplete character is kept, as the next bytes may complete it
+    /// Returns true if anything has been replaced
+    pub fn replace_invalid_utf8(&mut self, string_complete: bool) -> bool {
+        let mut replaced = false;
+        let mut start = 0;
+        while let Err(err) = std::str::from_utf8(&self.string_in_progress[start..]) {
+            let invalid_start = start + err.valid_up_to();
+            let invalid_len = match err.error_len() {
+                Some(invalid_len) => invalid_len,
+                None if string_complete => self.string_in_progress.len() - invalid_start,
+                None => break,
+            };
+            let replacement = char::REPLACEMENT_CHARACTER.to_string();
+            self.string_in_progress.splice(invalid_start..invalid_start + invalid_len, replacement.bytes());
+            start = invalid_start + replacement.len();
+            replaced = true;
+        }
+        replaced
+    }
+
+    /// Valid UTF-8 part of the bytes waiting to be flushed, without removing them
+    pub fn partial_str(&self) -> &str {
+        match std::str::from_utf8(&self.string_in_progress) {
+            Ok(data) => data,
+            Err(e) => std::str::from_utf8(&self.string_in_progress[..e.valid_up_to()]).unwrap(),
+        }
+    }
+
+    /// Number of bytes waiting to be flushed
+    #[inline]
+    pub fn buffered_len(&self) -> usize {
+        self.string_in_progress.len()
+    }
+
+    /// Whether the bytes waiting to be flushed end with a whitespace
+    #[inline]
+    pub fn ends_with_word_boundary(&self) -> bool {
+        self.string_in_progress.last().map(|c| c.is_ascii_whitespace()).unwrap_or(false)
+    }
+
+    /// Whether the bytes waiting to be flushed end with a complete sentence
+    /// Sentence ending punctuation must be followed by a whitespace, except for CJK full-width punctuation
+    pub fn ends_with_sentence_boundary(&self) -> bool {
+        match self.string_in_progress.as_slice() {
+            [.., b'\n'] => true,
+            [.., b'.' | b'!' | b'?', last] if last.is_ascii_whitespace() => true,
+            buffered => ["。", "！", "？"].iter().any(|stop| buffered.ends_with(stop.as_bytes())),
+        }
+    }
+
+    /// Same as flush, but also keeps back the last grapheme cluster, which may still be extended by the next characters
+    /// (ex: combining accents, ZWJ emoji sequences, regional indicator pairs)
+    pub fn flush_grapheme_safe(&mut self) -> Option<Value> {
+        if self.is_object_key || self.string_in_progress.is_empty() {
+            return None;
+        }
+        let valid_up_to = match std::str::from_utf8(&self.string_in_progress) {
+            Ok(data) => data.len(),
+            Err(e) => e.valid_up_to(),
+        };
+        let valid_data = std::str::from_utf8(&self.string_in_progress[..valid_up_to]).unwrap();
+        // Everything before the start of the last grapheme is complete
+        let complete_up_to = valid_data.grapheme_indices(true).next_back().map(|(idx, _)| idx).unwrap_or(0);
+        if complete_up_to == 0 {
+            return None;
+        }
+        let complete_prefix: Vec<u8> = self.string_in_progress.drain(..complete_up_to).collect();
+        Some(Value::String(String::from_utf8(complete_prefix).unwrap()))
+    }
 }
 
 impl StatusTrait for StatusString {
@@ -41,7 +145,14 @@ impl StatusTrait for StatusString {
                 if self.string_in_progress.len() > 0 {
                     let mut out_vec = Vec::<u8>::new(); // To be swapped with current data, since that one is no longer needed after this return
                     std::mem::swap(&mut self.string_in_progress, &mut out_vec);
-                    let final_string = String::from_utf8(out_vec)?;
+                    let final_string = match String::from_utf8(out_vec) {
+                        Ok(final_string) => final_string,
+                        Err(err) => {
+                            // Keep the data, so that the string can still be recovered
+                            self.string_in_progress = err.into_bytes();
+                            return Err("String is not in UTF8".into());
+                        }
+                    };
                     return Ok(Some((Some(Value::String(final_string)), Some(Status::Done(super::StatusDone::default())))))
                 } else {
                     // Empty string : may also happen if a flush has occured right before the end
@@ -94,6 +205,9 @@ impl StatusTrait for StatusString {
                     _ => return Err("Invalid JSON escaped character".into())
                 }
             },
+            (EscapeState::UTF8(_), escaped_char) if !escaped_char.is_ascii_hexdigit() => {
+                Err(format!("JSON contains invalid UTF digit : {}", *escaped_char as char).into())
+            },
             (EscapeState::UTF8(digits_so_far), escaped_char) => {
                 match digits_so_far.len() {
                     0 | 1 | 2 => {
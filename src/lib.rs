@@ -4,6 +4,15 @@ pub mod chunkers;
 pub mod ref_index_generator;
 pub mod json_stream_parser;
 pub mod json_key_path;
+pub mod decoders;
+pub mod stream_protocol;
+
+/// Reference counted pointer used for values shared with event handlers and parser outputs
+/// Becomes atomic with the "sync" feature, allowing the parser to be Send + Sync
+#[cfg(not(feature = "sync"))]
+pub type Shared<T> = std::rc::Rc<T>;
+#[cfg(feature = "sync")]
+pub type Shared<T> = std::sync::Arc<T>;
 
 pub fn byte_to_char(byte: &u8) -> EscapeDefault {
     std::ascii::escape_default(*byte)
@@ -1,28 +1,64 @@
-use std::{cell::RefCell, fmt::Debug, rc::Rc};
+use std::{fmt::Debug, sync::atomic::{AtomicUsize, Ordering}};
+
+use crate::{json_stream_parser::error::ParseError, Shared};
 
 // Cheaply clonable struct that manages unique references
-#[derive(Debug, PartialEq, Default)]
+// The counter is atomic so that the same code serves the "sync" feature, where clones are shared across threads
+#[derive(Debug, Default)]
 pub struct RefIndexGenerator {
-    internal_counter: Rc<RefCell<usize>>,
+    internal_counter: Shared<AtomicUsize>,
+    forked_at: Option<usize>, // Counter value when this generator was made by fork()
 }
 
 impl RefIndexGenerator {
     pub fn new() -> Self {
         Self {
-            internal_counter: Rc::new(RefCell::new(0))
+            internal_counter: Shared::new(AtomicUsize::new(0)),
+            forked_at: None
         }
     }
     pub fn generate(&self) -> usize {
-        let mut counter = self.internal_counter.borrow_mut();
-        *counter = *counter + 1;
-        *counter
+        self.internal_counter.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Makes an independent generator starting from the current counter value
+    /// Unlike clone(), indices generated by the fork are not visible to this generator until commit() is called
+    /// The fork needs exclusive use of the indices : if both generate indices before commit(), they overlap and commit() fails
+    pub fn fork(&self) -> Self {
+        let counter = self.internal_counter.load(Ordering::Relaxed);
+        Self {
+            internal_counter: Shared::new(AtomicUsize::new(counter)),
+            forked_at: Some(counter)
+        }
+    }
+
+    /// Brings the counter up to the one of a fork, so that indices used by the fork are never generated again
+    /// Fails if indices have been generated by both since the fork, as the same indices have then been handed out twice
+    pub fn commit(&self, forked: &RefIndexGenerator) -> Result<(), ParseError> {
+        let Some(forked_at) = forked.forked_at else {
+            return Err(ParseError::new("Only a generator made by fork() can be committed"));
+        };
+        let forked_counter = forked.internal_counter.load(Ordering::Relaxed);
+        if forked_counter == forked_at {
+            return Ok(()); // Nothing generated by the fork
+        }
+        self.internal_counter.compare_exchange(forked_at, forked_counter, Ordering::Relaxed, Ordering::Relaxed)
+            .map(|_| ())
+            .map_err(|_| ParseError::new(format!("Indices {} to {forked_counter} of the fork have also been generated since the fork", forked_at + 1)))
+    }
+}
+
+impl PartialEq for RefIndexGenerator {
+    fn eq(&self, other: &Self) -> bool {
+        self.internal_counter.load(Ordering::Relaxed) == other.internal_counter.load(Ordering::Relaxed)
     }
 }
 
 impl Clone for RefIndexGenerator {
     fn clone(&self) -> Self {
         Self {
-            internal_counter: Rc::clone(&self.internal_counter)
+            internal_counter: Shared::clone(&self.internal_counter),
+            forked_at: self.forked_at
         }
     }
-}
\ No newline at end of file
+}
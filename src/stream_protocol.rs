@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+use crate::json_stream_parser::{error::ParseError, parser_output::stream_protocol_output::STREAM_VAR_PREFIX};
+use dialect::{IndexBase, ProtocolDialect};
+
+pub mod binary_row_encoder;
+pub mod dialect;
+pub mod protocol_decoder;
+pub mod protocol_row;
+pub mod replay_log;
+pub mod sse;
+pub mod text_row_encoder;
+
+/// Version of the KurocoEdge stream protocol written by this library
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Header row, written first when enabled : `!ke={"version":1,"root":0,"ext":["close"]}`
+pub const ROW_HEADER: &str = "!ke=";
+
+// Optional parts of the protocol, as declared in the header
+pub const EXTENSION_COMPLETION_MARKERS: &str = "close"; // `{idx}!` rows once a node is complete
+pub const EXTENSION_END_ROWS: &str = "end"; // `!end` and `!error=` rows
+pub const EXTENSION_ESCAPE: &str = "escape"; // Literal strings that would be read as a reference are escaped
+pub const EXTENSION_BASE36: &str = "base36"; // Indices in base 36
+pub const EXTENSION_BASE62: &str = "base62"; // Indices in base 62
+
+/// Declares the version of the protocol, the index of the root node, and the enabled extensions
+/// Allows consumers to reject or adapt to a stream they do not fully understand
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolHeader {
+    pub version: u32,
+    pub root: usize,
+    #[serde(rename = "ext", default)]
+    pub extensions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>, // Reference prefix, when not the default one
+}
+
+impl ProtocolHeader {
+    pub fn new(root: usize) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            root,
+            extensions: Vec::new(),
+            prefix: None
+        }
+    }
+
+    /// Declares the dialect of the rows, except for the row terminator which must be known to read the header itself
+    pub fn with_dialect(mut self, dialect: &ProtocolDialect) -> Self {
+        if dialect.escape_literals {
+            self = self.with_extension(EXTENSION_ESCAPE);
+        }
+        match dialect.index_base {
+            IndexBase::Decimal => {},
+            IndexBase::Base36 => self = self.with_extension(EXTENSION_BASE36),
+            IndexBase::Base62 => self = self.with_extension(EXTENSION_BASE62),
+        }
+        if dialect.var_prefix() != STREAM_VAR_PREFIX {
+            self.prefix = Some(dialect.var_prefix().to_string());
+        }
+        self
+    }
+
+    /// Dialect declared by the header, with the default row terminator
+    pub fn dialect(&self) -> Result<ProtocolDialect, ParseError> {
+        let index_base = if self.has_extension(EXTENSION_BASE62) {
+            IndexBase::Base62
+        } else if self.has_extension(EXTENSION_BASE36) {
+            IndexBase::Base36
+        } else {
+            IndexBase::Decimal
+        };
+        let dialect = ProtocolDialect::default()
+            .with_index_base(index_base)
+            .with_escape_literals(self.has_extension(EXTENSION_ESCAPE));
+        match &self.prefix {
+            Some(prefix) => dialect.with_var_prefix(prefix.clone()),
+            None => Ok(dialect),
+        }
+    }
+
+    /// Builder style method to declare an extension (see EXTENSION_* constants)
+    pub fn with_extension(mut self, extension: &str) -> Self {
+        if !self.has_extension(extension) {
+            self.extensions.push(extension.to_string());
+        }
+        self
+    }
+
+    pub fn has_extension(&self, extension: &str) -> bool {
+        self.extensions.iter().any(|ext| ext == extension)
+    }
+
+    /// Whether this library can read a stream of this version
+    /// Unknown extensions do not prevent reading : they are rows the consumer may skip
+    pub fn is_supported(&self) -> bool {
+        self.version <= PROTOCOL_VERSION
+    }
+
+    /// Writes the header row, followed by the row terminator of the dialect
+    pub fn write_row(&self, output: &mut String, dialect: &ProtocolDialect) {
+        output.push_str(ROW_HEADER);
+        output.push_str(&serde_json::to_string(self).unwrap()); // Serializing this struct cannot fail
+        dialect.end_row(output);
+    }
+
+    /// Reads a header row, with or without its trailing new line (`\n` or `\r\n`)
+    pub fn parse(row: &str) -> Result<Self, ParseError> {
+        let row = row.strip_suffix('\n').map(|row| row.strip_suffix('\r').unwrap_or(row)).unwrap_or(row);
+        let data = row.strip_prefix(ROW_HEADER).ok_or_else(|| ParseError::new("Row is not a protocol header"))?;
+        serde_json::from_str(data).map_err(|err| ParseError::new(format!("Invalid protocol header : {err}")))
+    }
+}
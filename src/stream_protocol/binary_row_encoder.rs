@@ -0,0 +1,247 @@
+use serde_json::Value;
+
+use crate::{json_stream_parser::error::ParseError, Shared};
+
+use super::{protocol_row::{ProtocolOperator, ProtocolRow, ProtocolRowRef, RowEncoder, RowSink, RowValue, RowValueRef}, ProtocolHeader};
+
+// Operator byte, starting every row
+pub const BINARY_OP_ASSIGN: u8 = 0x01;
+pub const BINARY_OP_APPEND: u8 = 0x02;
+pub const BINARY_OP_CLOSE: u8 = 0x03;
+pub const BINARY_OP_HEADER: u8 = 0x04;
+pub const BINARY_OP_END: u8 = 0x05;
+pub const BINARY_OP_ERROR: u8 = 0x06;
+// Flags combined with the operator of assign and append rows
+pub const BINARY_FLAG_KEY: u8 = 0x40; // A length-prefixed key follows the index
+pub const BINARY_FLAG_REFERENCE: u8 = 0x80; // The payload is the index of a node instead of a JSON value
+const BINARY_OP_MASK: u8 = 0x3f;
+
+/// Compact binary form of the rows, for links between services : `{op}{idx}{key}{payload}`
+/// The operator is one byte, indices and lengths are LEB128 varints, keys and JSON payloads are length-prefixed
+/// Ex: `123+={"key":"$ke$124"}` is `[0xc2, 0x7b, 0x03, b"key", 0x7c]`
+/// Rows are self-delimiting : they are written one after the other, without terminator
+/// References being flagged, strings are always literal and never need escaping
+#[derive(Debug, Clone, Default)]
+pub struct BinaryRowEncoder;
+
+impl BinaryRowEncoder {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Reads the row at the start of the bytes, returning it along with its length
+    /// Returns None when the bytes end before the row does : more bytes are needed
+    pub fn decode_row(&self, bytes: &[u8]) -> Result<Option<(ProtocolRow, usize)>, ParseError> {
+        let mut reader = BinaryReader { bytes, position: 0 };
+        match reader.read_row() {
+            Ok(row) => Ok(Some((row, reader.position))),
+            Err(BinaryReadError::Incomplete) => Ok(None),
+            Err(BinaryReadError::Invalid(err)) => Err(err),
+        }
+    }
+}
+
+impl RowEncoder for BinaryRowEncoder {
+    type Output = Vec<u8>;
+
+    fn encode_row_ref(&self, row: ProtocolRowRef<'_>, output: &mut Vec<u8>) {
+        let op = match row.op {
+            ProtocolOperator::Assign => BINARY_OP_ASSIGN,
+            ProtocolOperator::Append => BINARY_OP_APPEND,
+            ProtocolOperator::Close => BINARY_OP_CLOSE,
+            ProtocolOperator::Header => BINARY_OP_HEADER,
+            ProtocolOperator::End => BINARY_OP_END,
+            ProtocolOperator::Error => BINARY_OP_ERROR,
+        };
+        match (row.idx, row.value) {
+            (_, RowValueRef::Header(header)) => {
+                output.push(BINARY_OP_HEADER);
+                write_bytes(output, &serde_json::to_vec(header).unwrap()); // Serializing a header cannot fail
+            },
+            (_, RowValueRef::Error(message)) => {
+                output.push(BINARY_OP_ERROR);
+                write_bytes(output, message.as_bytes());
+            },
+            (None, _) => output.push(BINARY_OP_END),
+            (Some(idx), value) => {
+                let flags = match value {
+                    RowValueRef::KeyValue(..) => BINARY_FLAG_KEY,
+                    RowValueRef::Reference(_) => BINARY_FLAG_REFERENCE,
+                    RowValueRef::KeyReference(..) => BINARY_FLAG_KEY | BINARY_FLAG_REFERENCE,
+                    _ => 0
+                };
+                output.push(op | flags);
+                write_varint(output, idx);
+                match value {
+                    RowValueRef::Value(value) => write_value(output, value),
+                    RowValueRef::KeyValue(key, value) => {
+                        write_bytes(output, key.as_bytes());
+                        write_value(output, value);
+                    },
+                    RowValueRef::Reference(ref_idx) => write_varint(output, ref_idx),
+                    RowValueRef::KeyReference(key, ref_idx) => {
+                        write_bytes(output, key.as_bytes());
+                        write_varint(output, ref_idx);
+                    },
+                    _ => {}
+                }
+            },
+        }
+    }
+}
+
+/// Writes the rows in their binary form as they are produced (see BinaryProtocolOutput)
+impl RowSink for Vec<u8> {
+    #[inline]
+    fn push_row(&mut self, row: ProtocolRowRef<'_>, _output: &mut String) {
+        BinaryRowEncoder.encode_row_ref(row, self);
+    }
+}
+
+/// Reads rows received in arbitrary chunks, keeping the bytes of an incomplete row until the next chunk
+#[derive(Debug, Clone, Default)]
+pub struct BinaryRowDecoder {
+    encoder: BinaryRowEncoder,
+    pending: Vec<u8>, // Start of a row whose end has not been received yet
+}
+
+impl BinaryRowDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns every row completed by the bytes
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<Vec<ProtocolRow>, ParseError> {
+        self.pending.extend_from_slice(bytes);
+        let mut rows = Vec::new();
+        let mut position = 0;
+        while let Some((row, row_len)) = self.encoder.decode_row(&self.pending[position..])? {
+            rows.push(row);
+            position += row_len;
+        }
+        self.pending.drain(..position);
+        Ok(rows)
+    }
+
+    /// Whether an incomplete row is waiting for more bytes, for ex. to detect a stream cut in the middle of a row
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
+
+#[inline]
+fn write_varint(output: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        output.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+#[inline]
+fn write_bytes(output: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(output, bytes.len());
+    output.extend_from_slice(bytes);
+}
+
+#[inline]
+fn write_value(output: &mut Vec<u8>, value: &Value) {
+    write_bytes(output, &serde_json::to_vec(value).unwrap()); // Serializing a Value cannot fail
+}
+
+enum BinaryReadError {
+    Incomplete,
+    Invalid(ParseError),
+}
+
+impl From<ParseError> for BinaryReadError {
+    fn from(err: ParseError) -> Self {
+        Self::Invalid(err)
+    }
+}
+
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BinaryReader<'a> {
+    fn read_row(&mut self) -> Result<ProtocolRow, BinaryReadError> {
+        let op_byte = self.read_byte()?;
+        let is_keyed = op_byte & BINARY_FLAG_KEY != 0;
+        let is_reference = op_byte & BINARY_FLAG_REFERENCE != 0;
+        let op = op_byte & BINARY_OP_MASK;
+        if (is_keyed || is_reference) && op != BINARY_OP_APPEND && op != BINARY_OP_ASSIGN {
+            return Err(ParseError::new(format!("Invalid row operator : {op_byte:#04x}")).into());
+        }
+        let row = match op {
+            BINARY_OP_HEADER => {
+                let header: ProtocolHeader = serde_json::from_slice(self.read_bytes()?)
+                    .map_err(|err| ParseError::new(format!("Invalid protocol header : {err}")))?;
+                ProtocolRow::header(header)
+            },
+            BINARY_OP_END => ProtocolRow::end(),
+            BINARY_OP_ERROR => ProtocolRow::error(self.read_str()?),
+            BINARY_OP_CLOSE => ProtocolRow::close(self.read_varint()?),
+            BINARY_OP_ASSIGN | BINARY_OP_APPEND => {
+                let idx = self.read_varint()?;
+                let key = if is_keyed { Some(self.read_str()?.to_string()) } else { None };
+                let value = match (key, is_reference) {
+                    (Some(key), true) => RowValue::KeyReference(key, self.read_varint()?),
+                    (Some(key), false) => RowValue::KeyValue(key, Shared::new(self.read_value()?)),
+                    (None, true) => RowValue::Reference(self.read_varint()?),
+                    (None, false) => RowValue::Value(Shared::new(self.read_value()?)),
+                };
+                if op == BINARY_OP_ASSIGN {
+                    match value {
+                        RowValue::Value(value) => ProtocolRow { idx: Some(idx), op: ProtocolOperator::Assign, value: RowValue::Value(value) },
+                        _ => return Err(ParseError::new("Only a value can be assigned to a node").into()),
+                    }
+                } else {
+                    ProtocolRow::append(idx, value)
+                }
+            },
+            _ => return Err(ParseError::new(format!("Invalid row operator : {op_byte:#04x}")).into()),
+        };
+        Ok(row)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, BinaryReadError> {
+        let byte = *self.bytes.get(self.position).ok_or(BinaryReadError::Incomplete)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn read_varint(&mut self) -> Result<usize, BinaryReadError> {
+        let mut value: usize = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_byte()?;
+            let bits = (byte & 0x7f) as usize;
+            if shift >= usize::BITS || (bits << shift) >> shift != bits {
+                return Err(ParseError::new("Varint is too large").into());
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn read_bytes(&mut self) -> Result<&'a [u8], BinaryReadError> {
+        let len = self.read_varint()?;
+        let end = self.position.checked_add(len).ok_or_else(|| ParseError::new("Length is too large"))?;
+        let bytes = self.bytes.get(self.position..end).ok_or(BinaryReadError::Incomplete)?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn read_str(&mut self) -> Result<&'a str, BinaryReadError> {
+        Ok(std::str::from_utf8(self.read_bytes()?).map_err(|err| ParseError::new(format!("String is not in UTF8 : {err}")))?)
+    }
+
+    fn read_value(&mut self) -> Result<Value, BinaryReadError> {
+        Ok(serde_json::from_slice(self.read_bytes()?).map_err(|err| ParseError::new(format!("Invalid row data : {err}")))?)
+    }
+}
@@ -0,0 +1,288 @@
+use serde_json::Value;
+
+use crate::json_stream_parser::{error::ParseError, parser_output::{stream_protocol_output::STREAM_VAR_PREFIX, write_json, write_json_str}};
+
+const BASE62_DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Writes the index with digits of the given radix
+#[inline]
+fn write_digits<const RADIX: usize>(output: &mut String, idx: usize) {
+    let mut digits = [0u8; 64];
+    let mut position = digits.len();
+    let mut remaining = idx;
+    loop {
+        position -= 1;
+        digits[position] = BASE62_DIGITS[remaining % RADIX];
+        remaining /= RADIX;
+        if remaining == 0 {
+            break;
+        }
+    }
+    // Digits are all ASCII
+    output.extend(digits[position..].iter().map(|digit| *digit as char));
+}
+
+/// JSON form of the prefix, without the quotes, written as is in references
+fn json_var_prefix(var_prefix: &str) -> String {
+    let mut json = String::with_capacity(var_prefix.len() + 2);
+    write_json(&mut json, var_prefix);
+    json[1..json.len() - 1].to_string()
+}
+
+pub(crate) fn is_valid_var_prefix(var_prefix: &str) -> bool {
+    let mut chars = var_prefix.chars();
+    let escape_char = chars.next();
+    escape_char.is_some() && chars.next() != escape_char
+}
+
+/// Encoding of node indices in rows. Base 36 and base 62 shorten rows of large streams
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IndexBase {
+    #[default]
+    Decimal,
+    Base36, // 0-9a-z
+    Base62, // 0-9a-zA-Z
+}
+
+impl IndexBase {
+    fn radix(&self) -> usize {
+        match self {
+            IndexBase::Decimal => 10,
+            IndexBase::Base36 => 36,
+            IndexBase::Base62 => 62,
+        }
+    }
+}
+
+/// A string read from a row payload, once unescaped
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtocolString {
+    Literal(String),
+    Reference(usize), // Reference to another node
+}
+
+/// Configurable parts of the row format, shared by StreamProtocolOutput, JsonProtocolChunker and ProtocolDecoder
+/// Both ends of a stream must use the same dialect (see ProtocolHeader::with_dialect)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtocolDialect {
+    var_prefix: String, // Prefix of node references, such as "$ke$5"
+    json_var_prefix: String,
+    pub row_terminator: String,
+    pub index_base: IndexBase,
+    // Literal strings that would be read as a reference get the first character of the prefix doubled (ex: "$$ke$5")
+    // Whole values are escaped, down to the strings nested in assigned objects and arrays
+    // String chunks appended with += never are
+    pub escape_literals: bool,
+}
+
+impl Default for ProtocolDialect {
+    fn default() -> Self {
+        Self {
+            var_prefix: STREAM_VAR_PREFIX.to_string(),
+            json_var_prefix: json_var_prefix(STREAM_VAR_PREFIX),
+            row_terminator: "\n".to_string(),
+            index_base: IndexBase::Decimal,
+            escape_literals: false,
+        }
+    }
+}
+
+impl ProtocolDialect {
+    /// The first character of the prefix marks references : the prefix must not be empty, nor start with that character twice
+    pub fn with_var_prefix(mut self, var_prefix: impl Into<String>) -> Result<Self, ParseError> {
+        let var_prefix = var_prefix.into();
+        if !is_valid_var_prefix(&var_prefix) {
+            return Err(ParseError::new(format!("Invalid reference prefix : {var_prefix}")));
+        }
+        self.json_var_prefix = json_var_prefix(&var_prefix);
+        self.var_prefix = var_prefix;
+        Ok(self)
+    }
+
+    pub fn var_prefix(&self) -> &str {
+        &self.var_prefix
+    }
+
+    pub fn with_row_terminator(mut self, row_terminator: impl Into<String>) -> Self {
+        self.row_terminator = row_terminator.into();
+        self
+    }
+
+    pub fn with_index_base(mut self, index_base: IndexBase) -> Self {
+        self.index_base = index_base;
+        self
+    }
+
+    pub fn with_escape_literals(mut self, escape_literals: bool) -> Self {
+        self.escape_literals = escape_literals;
+        self
+    }
+
+    #[inline]
+    fn escape_char(&self) -> char {
+        self.var_prefix.chars().next().unwrap() // Never empty, see with_var_prefix
+    }
+
+    #[inline]
+    pub fn write_index(&self, output: &mut String, idx: usize) {
+        // Digits are written by hand, as the formatting machinery of write! is slow for a row prefix
+        // The radix is a constant in each branch, which spares a division per digit
+        match self.index_base {
+            IndexBase::Decimal => write_digits::<10>(output, idx),
+            IndexBase::Base36 => write_digits::<36>(output, idx),
+            IndexBase::Base62 => write_digits::<62>(output, idx),
+        }
+    }
+
+    pub fn parse_index(&self, data: &str) -> Option<usize> {
+        if data.is_empty() {
+            return None;
+        }
+        let radix = self.index_base.radix();
+        data.bytes().try_fold(0usize, |idx, byte| {
+            let digit = BASE62_DIGITS[..radix].iter().position(|d| *d == byte)?;
+            idx.checked_mul(radix)?.checked_add(digit)
+        })
+    }
+
+    /// Whether the character may be part of an index
+    #[inline]
+    pub fn is_index_char(&self, c: char) -> bool {
+        match self.index_base {
+            IndexBase::Decimal => c.is_ascii_digit(),
+            IndexBase::Base36 => c.is_ascii_digit() || c.is_ascii_lowercase(),
+            IndexBase::Base62 => c.is_ascii_alphanumeric(),
+        }
+    }
+
+    /// Writes `{idx}{operator}`
+    #[inline]
+    pub fn write_row_start(&self, output: &mut String, idx: usize, operator: &str) {
+        self.write_index(output, idx);
+        output.push_str(operator);
+    }
+
+    #[inline]
+    pub fn end_row(&self, output: &mut String) {
+        output.push_str(&self.row_terminator);
+    }
+
+    /// Writes a reference to the node as a JSON string
+    #[inline]
+    pub fn write_reference(&self, output: &mut String, idx: usize) {
+        output.push('"');
+        output.push_str(&self.json_var_prefix);
+        self.write_index(output, idx); // Index digits never need escaping
+        output.push('"');
+    }
+
+    /// Whether the string starts with the prefix, once extra leading escape characters are removed (ex: "$ke$5", "$$ke$x")
+    /// Those are the only literals that need escaping : any other string is never read as a reference
+    fn is_colliding_literal(&self, value: &str) -> bool {
+        let escape_char = self.escape_char();
+        let prefix_rest = &self.var_prefix[escape_char.len_utf8()..];
+        let unescaped = value.trim_start_matches(escape_char);
+        unescaped.len() < value.len() && unescaped.starts_with(prefix_rest)
+    }
+
+    /// Whether the string is a literal escaped by write_literal
+    fn is_escaped_literal(&self, value: &str) -> bool {
+        let escape_char = self.escape_char();
+        self.escape_literals && self.is_colliding_literal(value) && value[escape_char.len_utf8()..].starts_with(escape_char)
+    }
+
+    /// Index of the reference written in the string, if it is exactly one as written by write_reference
+    fn parse_reference(&self, value: &str) -> Option<usize> {
+        let idx = self.parse_index(value.strip_prefix(self.var_prefix.as_str())?)?;
+        // Reject non canonical indices (ex: leading zeros), so that the string can be rebuilt from the index
+        let mut canonical = String::new();
+        self.write_index(&mut canonical, idx);
+        (canonical.len() + self.var_prefix.len() == value.len()).then_some(idx)
+    }
+
+    /// Writes a whole literal string as a JSON string, escaped if it could be mistaken for a reference
+    #[inline]
+    pub fn write_literal(&self, output: &mut String, value: &str) {
+        if self.escape_literals && self.is_colliding_literal(value) {
+            let escape_char = self.escape_char();
+            let mut escaped = String::with_capacity(value.len() + escape_char.len_utf8());
+            escaped.push(escape_char);
+            escaped.push_str(value);
+            write_json_str(output, &escaped);
+        } else {
+            write_json_str(output, value);
+        }
+    }
+
+    /// Writes an assigned value as JSON, escaping its strings as write_literal does, however deep they are
+    /// Object keys are never read as references, and are written as is
+    pub fn write_value(&self, output: &mut String, value: &Value) {
+        match value {
+            Value::String(value) => self.write_literal(output, value),
+            Value::Array(values) if self.escape_literals => {
+                output.push('[');
+                for (position, value) in values.iter().enumerate() {
+                    if position > 0 {
+                        output.push(',');
+                    }
+                    self.write_value(output, value);
+                }
+                output.push(']');
+            },
+            Value::Object(map) if self.escape_literals => {
+                output.push('{');
+                for (position, (key, value)) in map.iter().enumerate() {
+                    if position > 0 {
+                        output.push(',');
+                    }
+                    write_json(output, key);
+                    output.push(':');
+                    self.write_value(output, value);
+                }
+                output.push('}');
+            },
+            value => write_json(output, value),
+        }
+    }
+
+    /// Reverts write_value, for values that are known not to be references (ex: assigned values)
+    pub fn decode_value(&self, value: Value) -> Value {
+        if !self.escape_literals {
+            return value;
+        }
+        match value {
+            Value::String(value) => Value::String(self.decode_literal(value)),
+            Value::Array(values) => Value::Array(values.into_iter().map(|value| self.decode_value(value)).collect()),
+            Value::Object(map) => Value::Object(map.into_iter().map(|(key, value)| (key, self.decode_value(value))).collect()),
+            value => value,
+        }
+    }
+
+    /// Reverts write_literal, for whole strings that are known not to be references (ex: assigned strings)
+    pub fn decode_literal(&self, mut value: String) -> String {
+        if self.is_escaped_literal(&value) {
+            value.remove(0);
+        }
+        value
+    }
+
+    /// Reads a whole string written either by write_literal or by write_reference
+    pub fn decode_string(&self, value: String) -> Result<ProtocolString, ParseError> {
+        if let Some(idx) = self.parse_reference(&value) {
+            return Ok(ProtocolString::Reference(idx));
+        }
+        if self.escape_literals && self.is_colliding_literal(&value) && !self.is_escaped_literal(&value) {
+            // Looks like a reference, but is neither a valid one nor escaped
+            return Err(ParseError::new(format!("Invalid reference : {value}")));
+        }
+        Ok(ProtocolString::Literal(self.decode_literal(value)))
+    }
+
+    /// Reads a string appended with += : either a reference, or a string chunk which is never escaped
+    pub fn decode_appended_string(&self, value: String) -> ProtocolString {
+        match self.parse_reference(&value) {
+            Some(idx) => ProtocolString::Reference(idx),
+            None => ProtocolString::Literal(value),
+        }
+    }
+}
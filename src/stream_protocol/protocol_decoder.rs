@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexMap;
+use serde_json::Value;
+
+use crate::json_stream_parser::error::ParseError;
+
+use super::{dialect::ProtocolDialect, protocol_row::{ProtocolOperator, ProtocolRow, RowValue}, text_row_encoder::TextRowEncoder, ProtocolHeader};
+
+/// Value within a node : either a plain value or a reference to another node
+#[derive(Debug, Clone)]
+enum Slot {
+    Literal(Value),
+    Reference(usize),
+}
+
+#[derive(Debug, Clone)]
+enum DecodedNode {
+    Basic(Value),
+    String(String),
+    Array(Vec<Slot>),
+    Object(IndexMap<String, Slot>),
+}
+
+/// Rebuilds the JSON value from the rows written by StreamProtocolOutput or JsonProtocolChunker
+/// Allows Rust consumers (and tests) to read a stream, whatever its dialect
+pub struct ProtocolDecoder {
+    encoder: TextRowEncoder, // Reads the text rows
+    nodes: HashMap<usize, DecodedNode>,
+    root_idx: Option<usize>, // Declared by the header, or else the first assigned node
+    closed_nodes: HashSet<usize>,
+    header: Option<ProtocolHeader>,
+    is_finished: bool,
+    error: Option<String>,
+}
+
+impl ProtocolDecoder {
+    pub fn new(dialect: ProtocolDialect) -> Self {
+        Self {
+            encoder: TextRowEncoder::new(dialect),
+            nodes: HashMap::new(),
+            root_idx: None,
+            closed_nodes: HashSet::new(),
+            header: None,
+            is_finished: false,
+            error: None,
+        }
+    }
+
+    /// Applies every complete row of the text, split with the row terminator of the dialect
+    pub fn apply_rows(&mut self, rows: &str) -> Result<(), ParseError> {
+        let row_terminator = self.dialect().row_terminator.clone();
+        rows.split(row_terminator.as_str())
+            .filter(|row| !row.is_empty())
+            .try_for_each(|row| self.apply_row(row))
+    }
+
+    /// Applies one text row, with or without its terminator
+    /// A header row switches to the dialect it declares, except for the row terminator
+    pub fn apply_row(&mut self, row: &str) -> Result<(), ParseError> {
+        let row = self.encoder.decode_row(row)?;
+        if let RowValue::Header(header) = &row.value {
+            if header.is_supported() {
+                let dialect = header.dialect()?.with_row_terminator(self.dialect().row_terminator.clone());
+                self.encoder.set_dialect(dialect);
+            }
+        }
+        self.apply(row)
+    }
+
+    /// Applies one structured row (see JsonProtocolChunker::rows and ProtocolRowOutput)
+    pub fn apply(&mut self, row: ProtocolRow) -> Result<(), ParseError> {
+        match (row.op, row.idx, row.value) {
+            (_, _, RowValue::Header(header)) => {
+                if !header.is_supported() {
+                    return Err(ParseError::new(format!("Unsupported protocol version : {}", header.version)));
+                }
+                self.root_idx = Some(header.root);
+                self.header = Some(header);
+            },
+            (_, _, RowValue::Error(message)) => self.error = Some(message),
+            (ProtocolOperator::End, _, _) => self.is_finished = true,
+            (ProtocolOperator::Close, Some(idx), _) => {
+                self.closed_nodes.insert(idx);
+            },
+            (ProtocolOperator::Assign, Some(idx), RowValue::Value(value)) => self.assign(idx, value.as_ref().clone()),
+            (ProtocolOperator::Append, Some(idx), value) => return self.append(idx, value),
+            _ => return Err(ParseError::new("Invalid row")),
+        }
+        Ok(())
+    }
+
+    fn assign(&mut self, idx: usize, value: Value) {
+        let node = match value {
+            Value::String(value) => DecodedNode::String(value),
+            Value::Array(values) => DecodedNode::Array(values.into_iter().map(Slot::Literal).collect()),
+            Value::Object(map) => DecodedNode::Object(map.into_iter().map(|(key, value)| (key, Slot::Literal(value))).collect()),
+            value => DecodedNode::Basic(value),
+        };
+        self.root_idx.get_or_insert(idx);
+        self.nodes.insert(idx, node);
+    }
+
+    fn append(&mut self, idx: usize, value: RowValue) -> Result<(), ParseError> {
+        match (self.nodes.get_mut(&idx), value) {
+            (Some(DecodedNode::String(existing)), RowValue::Value(value)) => match value.as_ref() {
+                Value::String(value) => existing.push_str(value),
+                _ => return Err(ParseError::new(format!("Only strings can be appended to the string node {idx}"))),
+            },
+            (Some(DecodedNode::String(existing)), RowValue::Reference(ref_idx)) => {
+                // String chunks are never references : without escaping, a chunk may only look like one
+                let dialect = self.encoder.dialect();
+                existing.push_str(dialect.var_prefix());
+                dialect.write_index(existing, ref_idx);
+            },
+            (Some(DecodedNode::Array(existing)), RowValue::Value(value)) => existing.push(Slot::Literal(value.as_ref().clone())),
+            (Some(DecodedNode::Array(existing)), RowValue::Reference(ref_idx)) => existing.push(Slot::Reference(ref_idx)),
+            (Some(DecodedNode::Object(existing)), RowValue::KeyValue(key, value)) => {
+                existing.insert(key, Slot::Literal(value.as_ref().clone()));
+            },
+            (Some(DecodedNode::Object(existing)), RowValue::KeyReference(key, ref_idx)) => {
+                existing.insert(key, Slot::Reference(ref_idx));
+            },
+            (None, _) => return Err(ParseError::new(format!("Append to unknown node {idx}"))),
+            _ => return Err(ParseError::new(format!("Invalid append to node {idx}"))),
+        }
+        Ok(())
+    }
+
+    /// Current value of the document, with every reference resolved
+    /// Nodes referenced but not received yet are null
+    pub fn value(&self) -> Option<Value> {
+        self.root_idx.filter(|idx| self.nodes.contains_key(idx)).map(|idx| self.node_value(idx, &mut Vec::new()))
+    }
+
+    fn node_value(&self, idx: usize, resolving: &mut Vec<usize>) -> Value {
+        if resolving.contains(&idx) {
+            return Value::Null; // A node referencing itself is invalid : do not loop forever
+        }
+        resolving.push(idx);
+        let value = match self.nodes.get(&idx) {
+            None => Value::Null,
+            Some(DecodedNode::Basic(value)) => value.clone(),
+            Some(DecodedNode::String(value)) => Value::String(value.clone()),
+            Some(DecodedNode::Array(slots)) => Value::Array(slots.iter().map(|slot| self.slot_value(slot, resolving)).collect()),
+            Some(DecodedNode::Object(slots)) => Value::Object(slots.iter().map(|(key, slot)| (key.clone(), self.slot_value(slot, resolving))).collect()),
+        };
+        resolving.pop();
+        value
+    }
+
+    fn slot_value(&self, slot: &Slot, resolving: &mut Vec<usize>) -> Value {
+        match slot {
+            Slot::Literal(value) => value.clone(),
+            Slot::Reference(idx) => self.node_value(*idx, resolving),
+        }
+    }
+
+    /// Whether a close row has been received for the node (see StreamProtocolOutput::with_completion_markers)
+    pub fn is_node_closed(&self, idx: usize) -> bool {
+        self.closed_nodes.contains(&idx)
+    }
+
+    pub fn root_idx(&self) -> Option<usize> {
+        self.root_idx
+    }
+
+    pub fn header(&self) -> Option<&ProtocolHeader> {
+        self.header.as_ref()
+    }
+
+    pub fn dialect(&self) -> &ProtocolDialect {
+        self.encoder.dialect()
+    }
+
+    /// Whether the end row has been received (see StreamProtocolOutput::with_end_rows)
+    pub fn is_finished(&self) -> bool {
+        self.is_finished
+    }
+
+    /// Message of the error row, if any
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::Shared;
+
+use super::ProtocolHeader;
+
+/// Operation of a row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtocolOperator {
+    Assign, // `=` : sets the value of the node
+    Append, // `+=` : appends to a string, an array or an object
+    Close, // `!` : the node is complete
+    Header, // Stream level rows, without index
+    End,
+    Error,
+}
+
+/// Data of a row. References to other nodes are kept apart from values, so that they never need escaping
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RowValue {
+    None, // Close and end rows
+    Value(Shared<Value>), // Strings are always literal
+    KeyValue(String, Shared<Value>), // Basic value appended to an object under the key
+    Reference(usize), // Node appended to an array
+    KeyReference(String, usize), // Node appended to an object under the key
+    Header(ProtocolHeader),
+    Error(String), // Error message
+}
+
+/// One row of the KurocoEdge stream protocol, before being encoded (see TextRowEncoder)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolRow {
+    pub idx: Option<usize>, // None for stream level rows (header, end, error)
+    pub op: ProtocolOperator,
+    pub value: RowValue,
+}
+
+impl ProtocolRow {
+    pub fn assign(idx: usize, value: Value) -> Self {
+        Self { idx: Some(idx), op: ProtocolOperator::Assign, value: RowValue::Value(Shared::new(value)) }
+    }
+
+    pub fn append(idx: usize, value: RowValue) -> Self {
+        Self { idx: Some(idx), op: ProtocolOperator::Append, value }
+    }
+
+    pub fn close(idx: usize) -> Self {
+        Self { idx: Some(idx), op: ProtocolOperator::Close, value: RowValue::None }
+    }
+
+    pub fn header(header: ProtocolHeader) -> Self {
+        Self { idx: None, op: ProtocolOperator::Header, value: RowValue::Header(header) }
+    }
+
+    pub fn end() -> Self {
+        Self { idx: None, op: ProtocolOperator::End, value: RowValue::None }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self { idx: None, op: ProtocolOperator::Error, value: RowValue::Error(message.into()) }
+    }
+
+    pub fn as_row_ref(&self) -> ProtocolRowRef<'_> {
+        let value = match &self.value {
+            RowValue::None => RowValueRef::None,
+            RowValue::Value(value) => RowValueRef::Value(value),
+            RowValue::KeyValue(key, value) => RowValueRef::KeyValue(key, value),
+            RowValue::Reference(ref_idx) => RowValueRef::Reference(*ref_idx),
+            RowValue::KeyReference(key, ref_idx) => RowValueRef::KeyReference(key, *ref_idx),
+            RowValue::Header(header) => RowValueRef::Header(header),
+            RowValue::Error(message) => RowValueRef::Error(message),
+        };
+        ProtocolRowRef { idx: self.idx, op: self.op, value }
+    }
+}
+
+/// Borrowed data of a row (see RowValue)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RowValueRef<'a> {
+    None,
+    Value(&'a Value),
+    KeyValue(&'a str, &'a Value),
+    Reference(usize),
+    KeyReference(&'a str, usize),
+    Header(&'a ProtocolHeader),
+    Error(&'a str),
+}
+
+/// Borrowed row, so that rows can be encoded as they are produced without allocating them (see RowSink)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProtocolRowRef<'a> {
+    pub idx: Option<usize>,
+    pub op: ProtocolOperator,
+    pub value: RowValueRef<'a>,
+}
+
+impl<'a> ProtocolRowRef<'a> {
+    pub fn assign(idx: usize, value: &'a Value) -> Self {
+        Self { idx: Some(idx), op: ProtocolOperator::Assign, value: RowValueRef::Value(value) }
+    }
+
+    pub fn append(idx: usize, value: RowValueRef<'a>) -> Self {
+        Self { idx: Some(idx), op: ProtocolOperator::Append, value }
+    }
+
+    pub fn close(idx: usize) -> Self {
+        Self { idx: Some(idx), op: ProtocolOperator::Close, value: RowValueRef::None }
+    }
+
+    pub fn header(header: &'a ProtocolHeader) -> Self {
+        Self { idx: None, op: ProtocolOperator::Header, value: RowValueRef::Header(header) }
+    }
+
+    pub fn end() -> Self {
+        Self { idx: None, op: ProtocolOperator::End, value: RowValueRef::None }
+    }
+
+    pub fn error(message: &'a str) -> Self {
+        Self { idx: None, op: ProtocolOperator::Error, value: RowValueRef::Error(message) }
+    }
+
+    pub fn to_row(&self) -> ProtocolRow {
+        let value = match self.value {
+            RowValueRef::None => RowValue::None,
+            RowValueRef::Value(value) => RowValue::Value(Shared::new(value.clone())),
+            RowValueRef::KeyValue(key, value) => RowValue::KeyValue(key.to_string(), Shared::new(value.clone())),
+            RowValueRef::Reference(ref_idx) => RowValue::Reference(ref_idx),
+            RowValueRef::KeyReference(key, ref_idx) => RowValue::KeyReference(key.to_string(), ref_idx),
+            RowValueRef::Header(header) => RowValue::Header(header.clone()),
+            RowValueRef::Error(message) => RowValue::Error(message.to_string()),
+        };
+        ProtocolRow { idx: self.idx, op: self.op, value }
+    }
+}
+
+/// Renders rows into a given format
+pub trait RowEncoder {
+    type Output;
+
+    fn encode_row_ref(&self, row: ProtocolRowRef<'_>, output: &mut Self::Output);
+
+    #[inline]
+    fn encode_row(&self, row: &ProtocolRow, output: &mut Self::Output) {
+        self.encode_row_ref(row.as_row_ref(), output);
+    }
+}
+
+/// Receives the rows of ProtocolRowOutput as they are produced, along with the output of the parser
+pub trait RowSink {
+    fn push_row(&mut self, row: ProtocolRowRef<'_>, output: &mut String);
+}
+
+/// Collects the rows, leaving the output untouched
+impl RowSink for Vec<ProtocolRow> {
+    #[inline]
+    fn push_row(&mut self, row: ProtocolRowRef<'_>, _output: &mut String) {
+        self.push(row.to_row());
+    }
+}
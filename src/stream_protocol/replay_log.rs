@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+
+/// Result of ReplayLog::replay_from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Replay {
+    /// Rows with their sequence number, from the requested one to the last recorded one
+    Rows(Vec<(u64, String)>),
+    /// Part of the requested rows is not in the log anymore (or has never been) : the client must be resent the whole document
+    Resync,
+}
+
+/// Bounded log of the last rows sent, numbered with sequence numbers, for clients reconnecting mid-stream
+/// Sequence numbers match the SSE event ids when every row is its own event (see SseOutput) :
+/// a client reconnecting with `Last-Event-ID: n` is replayed from n + 1
+#[derive(Debug, Clone)]
+pub struct ReplayLog {
+    rows: VecDeque<(u64, String)>,
+    next_seq: u64,
+    max_rows: usize,
+    max_bytes: Option<usize>,
+    bytes: usize, // Total length of the rows kept
+    row_terminator: String, // Splits the output of record_rows
+}
+
+impl ReplayLog {
+    /// Log keeping at most max_rows rows, the oldest ones being evicted first
+    pub fn new(max_rows: usize) -> Self {
+        Self {
+            rows: VecDeque::new(),
+            next_seq: 0,
+            max_rows,
+            max_bytes: None,
+            bytes: 0,
+            row_terminator: "\n".to_string()
+        }
+    }
+
+    /// Also bounds the total length of the rows kept
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Sequence number of the first row, for ex. to match the first id of an SseFramer
+    pub fn with_first_seq(mut self, seq: u64) -> Self {
+        self.next_seq = seq;
+        self
+    }
+
+    /// Row terminator of the dialect, when not the default one (see ProtocolDialect::with_row_terminator)
+    pub fn with_row_terminator(mut self, row_terminator: impl Into<String>) -> Self {
+        self.row_terminator = row_terminator.into();
+        self
+    }
+
+    /// Records a single row. Returns its sequence number
+    pub fn record(&mut self, row: impl Into<String>) -> u64 {
+        let row = row.into();
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.bytes += row.len();
+        self.rows.push_back((seq, row));
+        self.evict();
+        seq
+    }
+
+    /// Records every row of a text, each row keeping its terminator
+    pub fn record_rows(&mut self, rows: &str) {
+        let row_terminator = self.row_terminator.clone();
+        let mut row_start = 0;
+        for (position, terminator) in rows.match_indices(row_terminator.as_str()) {
+            let row_end = position + terminator.len();
+            self.record(&rows[row_start..row_end]);
+            row_start = row_end;
+        }
+        if row_start < rows.len() {
+            self.record(&rows[row_start..]); // Row without terminator
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.rows.len() > self.max_rows || self.max_bytes.is_some_and(|max_bytes| self.bytes > max_bytes) {
+            match self.rows.pop_front() {
+                Some((_, row)) => self.bytes -= row.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Rows from the sequence number seq included
+    /// A client which received everything (seq being next_seq) gets no rows
+    pub fn replay_from(&self, seq: u64) -> Replay {
+        let first_seq = self.first_seq();
+        if seq < first_seq || seq > self.next_seq {
+            return Replay::Resync;
+        }
+        let skip = (seq - first_seq) as usize;
+        Replay::Rows(self.rows.iter().skip(skip).cloned().collect())
+    }
+
+    /// Sequence number of the oldest row kept, or next_seq when the log is empty
+    pub fn first_seq(&self) -> u64 {
+        self.rows.front().map(|(seq, _)| *seq).unwrap_or(self.next_seq)
+    }
+
+    /// Sequence number of the next row recorded
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
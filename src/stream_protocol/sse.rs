@@ -0,0 +1,198 @@
+use std::{future::Future, pin::Pin, task::{Context, Poll}, time::Duration};
+
+use futures::Stream;
+use futures_timer::Delay;
+use pin_project::pin_project;
+
+use crate::json_stream_parser::parser_options::SharedClock;
+
+pub const SSE_KEEP_ALIVE_COMMENT: &str = ": keep-alive\n\n";
+
+/// Frames protocol rows as Server-Sent Events (`text/event-stream`), with monotonic ids for `Last-Event-ID` resumption
+/// Ex: `id: 3\nevent: rows\ndata: 0+="Hello"\n\n`
+/// Rows are split on new lines, each one being a `data:` line : clients get them back joined with new lines
+#[derive(Debug, Clone)]
+pub struct SseFramer {
+    next_id: u64,
+    event_name: Option<String>, // Written as `event:`, so that clients can listen to it with addEventListener
+    row_terminator: String, // Splits the rows given to write_row_events
+    keep_alive: Option<(Duration, SharedClock)>, // Idle time after which a keep-alive comment is due
+    last_write: Option<Duration>, // Time of the last event or comment, according to the keep-alive clock
+}
+
+impl SseFramer {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            event_name: None,
+            row_terminator: "\n".to_string(),
+            keep_alive: None,
+            last_write: None
+        }
+    }
+
+    /// Starts numbering at the given id, for ex. right after the `Last-Event-ID` of a reconnecting client
+    pub fn with_first_id(mut self, id: u64) -> Self {
+        self.next_id = id;
+        self
+    }
+
+    pub fn with_event_name(mut self, event_name: impl Into<String>) -> Self {
+        self.event_name = Some(event_name.into());
+        self
+    }
+
+    /// Row terminator of the dialect, when not the default one (see ProtocolDialect::with_row_terminator)
+    /// Keeps one event per row, so that event ids match the sequence numbers of a ReplayLog
+    pub fn with_row_terminator(mut self, row_terminator: impl Into<String>) -> Self {
+        self.row_terminator = row_terminator.into();
+        self
+    }
+
+    /// Enables keep_alive_if_idle, writing a comment once nothing has been written for the given interval
+    pub fn with_keep_alive(mut self, interval: Duration, clock: SharedClock) -> Self {
+        self.last_write = Some(clock.now());
+        self.keep_alive = Some((interval, clock));
+        self
+    }
+
+    /// Id of the next event
+    pub fn next_id(&self) -> u64 {
+        self.next_id
+    }
+
+    /// Writes the rows as a single event. Nothing is written for empty rows
+    /// Returns the id of the event
+    pub fn write_event(&mut self, output: &mut String, rows: &str) -> Option<u64> {
+        let mut lines = Self::lines(rows).peekable();
+        lines.peek()?;
+        let id = self.next_id;
+        self.next_id += 1;
+        output.push_str("id: ");
+        output.push_str(&id.to_string());
+        output.push('\n');
+        if let Some(event_name) = &self.event_name {
+            output.push_str("event: ");
+            output.push_str(event_name);
+            output.push('\n');
+        }
+        for line in lines {
+            output.push_str("data: ");
+            output.push_str(line);
+            output.push('\n');
+        }
+        output.push('\n');
+        self.touch();
+        Some(id)
+    }
+
+    /// Writes one event per row, rows being split with the row terminator
+    pub fn write_row_events(&mut self, output: &mut String, rows: &str) {
+        let row_terminator = std::mem::take(&mut self.row_terminator);
+        for row in rows.split(row_terminator.as_str()) {
+            self.write_event(output, row);
+        }
+        self.row_terminator = row_terminator;
+    }
+
+    /// Writes a comment, ignored by clients, keeping proxies from closing an idle connection
+    pub fn write_keep_alive(&mut self, output: &mut String) {
+        output.push_str(SSE_KEEP_ALIVE_COMMENT);
+        self.touch();
+    }
+
+    /// Writes a keep-alive comment if nothing has been written for the interval given to with_keep_alive
+    /// Meant to be called on a timer while waiting for the upstream. Returns whether a comment has been written
+    pub fn keep_alive_if_idle(&mut self, output: &mut String) -> bool {
+        let Some((interval, clock)) = &self.keep_alive else {
+            return false;
+        };
+        let now = clock.now();
+        if self.last_write.is_some_and(|last_write| now.saturating_sub(last_write) < *interval) {
+            return false;
+        }
+        self.write_keep_alive(output);
+        true
+    }
+
+    /// Time left until a keep-alive comment is due, None when keep-alive is disabled
+    pub fn keep_alive_due_in(&self) -> Option<Duration> {
+        let (interval, clock) = self.keep_alive.as_ref()?;
+        let idle = self.last_write.map(|last_write| clock.now().saturating_sub(last_write)).unwrap_or(*interval);
+        Some(interval.saturating_sub(idle))
+    }
+
+    fn touch(&mut self) {
+        if let Some((_, clock)) = &self.keep_alive {
+            self.last_write = Some(clock.now());
+        }
+    }
+
+    /// Non empty lines of the rows. A carriage return would end the line for SSE clients, so it is dropped along with the new line
+    fn lines(rows: &str) -> impl Iterator<Item = &str> {
+        rows.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)).filter(|line| !line.is_empty())
+    }
+}
+
+impl Default for SseFramer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Frames every item of a stream of rows (ex: JsonProtocolChunkStream) as an SSE event
+/// While the inner stream is pending, a keep-alive comment is yielded when due (see SseFramer::with_keep_alive)
+#[pin_project]
+pub struct SseStream<S> {
+    #[pin]
+    inner: S,
+    framer: SseFramer,
+    keep_alive_timer: Option<Delay>, // Wakes the task once a keep-alive comment is due, while the inner stream is pending
+}
+
+impl<S> SseStream<S>
+where
+    S: Stream<Item = String>
+{
+    pub fn new(inner: S, framer: SseFramer) -> Self {
+        Self { inner, framer, keep_alive_timer: None }
+    }
+
+    pub fn framer(&self) -> &SseFramer {
+        &self.framer
+    }
+}
+
+impl<S> Stream for SseStream<S>
+where
+    S: Stream<Item = String>
+{
+    type Item = String;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let mut output = String::new();
+        match this.inner.poll_next(cx) {
+            Poll::Ready(Some(rows)) => {
+                this.framer.write_event(&mut output, &rows);
+                Poll::Ready(Some(output))
+            },
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => loop {
+                if this.framer.keep_alive_if_idle(&mut output) {
+                    *this.keep_alive_timer = None;
+                    return Poll::Ready(Some(output));
+                }
+                let Some(due_in) = this.framer.keep_alive_due_in() else {
+                    return Poll::Pending;
+                };
+                // Deadline moves as events are written : the timer is set again on every poll
+                let timer = this.keep_alive_timer.get_or_insert_with(|| Delay::new(due_in));
+                timer.reset(due_in);
+                if Pin::new(timer).poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+            },
+        }
+    }
+}
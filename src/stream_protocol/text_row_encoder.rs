@@ -0,0 +1,141 @@
+use serde_json::{json, Value};
+
+use crate::{json_stream_parser::{error::ParseError, parser_output::{stream_protocol_output::{OPERATOR_APPEND, OPERATOR_ASSIGN, OPERATOR_CLOSE, ROW_END, ROW_ERROR}, write_json, write_json_str}}, Shared};
+
+use super::{dialect::{ProtocolDialect, ProtocolString}, protocol_row::{ProtocolOperator, ProtocolRow, ProtocolRowRef, RowEncoder, RowSink, RowValue, RowValueRef}, ProtocolHeader, ROW_HEADER};
+
+/// Text form of the rows, as sent to browsers : `{idx}{operator}{json}` followed by the row terminator
+#[derive(Debug, Clone, Default)]
+pub struct TextRowEncoder {
+    dialect: ProtocolDialect,
+}
+
+impl TextRowEncoder {
+    pub fn new(dialect: ProtocolDialect) -> Self {
+        Self { dialect }
+    }
+
+    pub fn dialect(&self) -> &ProtocolDialect {
+        &self.dialect
+    }
+
+    pub fn set_dialect(&mut self, dialect: ProtocolDialect) {
+        self.dialect = dialect;
+    }
+
+    /// Header also declaring the dialect the rows are written in
+    pub fn complete_header(&self, header: &ProtocolHeader) -> ProtocolHeader {
+        let dialect_header = ProtocolHeader { extensions: Vec::new(), ..header.clone() }.with_dialect(&self.dialect);
+        header.extensions.iter().fold(dialect_header, |dialect_header, extension| dialect_header.with_extension(extension))
+    }
+
+    #[inline]
+    fn write_key(&self, output: &mut String, key: &str) {
+        output.push('{');
+        write_json_str(output, key);
+        output.push(':');
+    }
+
+    /// Reads one row, with or without its terminator
+    pub fn decode_row(&self, row: &str) -> Result<ProtocolRow, ParseError> {
+        let row = row.strip_suffix(self.dialect.row_terminator.as_str()).unwrap_or(row);
+        if row.starts_with(ROW_HEADER) {
+            return Ok(ProtocolRow::header(ProtocolHeader::parse(row)?));
+        }
+        if row == ROW_END {
+            return Ok(ProtocolRow::end());
+        }
+        if let Some(data) = row.strip_prefix(ROW_ERROR) {
+            let data: Value = serde_json::from_str(data).map_err(|err| ParseError::new(format!("Invalid error row : {err}")))?;
+            return Ok(ProtocolRow::error(data.get("message").and_then(|message| message.as_str()).unwrap_or_default()));
+        }
+
+        let op_position = row.find(|c: char| !self.dialect.is_index_char(c)).unwrap_or(row.len());
+        let idx = self.dialect.parse_index(&row[..op_position]).ok_or_else(|| ParseError::new(format!("Invalid row : {row}")))?;
+        let operation = &row[op_position..];
+        if operation == OPERATOR_CLOSE {
+            return Ok(ProtocolRow::close(idx));
+        }
+        let (op, data) = if let Some(data) = operation.strip_prefix(OPERATOR_APPEND) {
+            (ProtocolOperator::Append, data)
+        } else if let Some(data) = operation.strip_prefix(OPERATOR_ASSIGN) {
+            (ProtocolOperator::Assign, data)
+        } else {
+            return Err(ParseError::new(format!("Invalid row operator : {row}")));
+        };
+        let data: Value = serde_json::from_str(data).map_err(|err| ParseError::new(format!("Invalid row data : {err}")))?;
+        let value = match (op, data) {
+            (ProtocolOperator::Append, Value::Object(map)) => {
+                let mut entries = map.into_iter();
+                match (entries.next(), entries.next()) {
+                    (Some((key, Value::String(value))), None) => match self.dialect.decode_string(value)? {
+                        ProtocolString::Reference(ref_idx) => RowValue::KeyReference(key, ref_idx),
+                        ProtocolString::Literal(value) => RowValue::KeyValue(key, Shared::new(Value::String(value))),
+                    },
+                    (Some((key, value)), None) => RowValue::KeyValue(key, Shared::new(self.dialect.decode_value(value))),
+                    _ => return Err(ParseError::new(format!("Appending to an object takes a single key : {row}"))),
+                }
+            },
+            (ProtocolOperator::Append, Value::String(value)) => match self.dialect.decode_appended_string(value) {
+                ProtocolString::Reference(ref_idx) => RowValue::Reference(ref_idx),
+                ProtocolString::Literal(value) => RowValue::Value(Shared::new(Value::String(value))),
+            },
+            // A reference is never assigned : the strings are literals
+            (_, value) => RowValue::Value(Shared::new(self.dialect.decode_value(value))),
+        };
+        Ok(ProtocolRow { idx: Some(idx), op, value })
+    }
+}
+
+impl RowEncoder for TextRowEncoder {
+    type Output = String;
+
+    fn encode_row_ref(&self, row: ProtocolRowRef<'_>, output: &mut String) {
+        match (row.idx, row.value) {
+            (_, RowValueRef::Header(header)) => {
+                self.complete_header(header).write_row(output, &self.dialect);
+                return;
+            },
+            (_, RowValueRef::Error(message)) => {
+                output.push_str(ROW_ERROR);
+                write_json(output, &json!({"message": message}));
+            },
+            (None, _) => output.push_str(ROW_END),
+            (Some(idx), value) => {
+                let operator = match row.op {
+                    ProtocolOperator::Assign => OPERATOR_ASSIGN,
+                    ProtocolOperator::Close => OPERATOR_CLOSE,
+                    _ => OPERATOR_APPEND,
+                };
+                self.dialect.write_row_start(output, idx, operator);
+                match value {
+                    // A string appended with += is a chunk of a string node, which is never escaped
+                    RowValueRef::Value(Value::String(value)) if row.op == ProtocolOperator::Append => write_json_str(output, value),
+                    RowValueRef::Value(value) if row.op == ProtocolOperator::Append => write_json(output, value),
+                    RowValueRef::Value(value) => self.dialect.write_value(output, value),
+                    RowValueRef::KeyValue(key, value) => {
+                        self.write_key(output, key);
+                        self.dialect.write_value(output, value);
+                        output.push('}');
+                    },
+                    RowValueRef::Reference(ref_idx) => self.dialect.write_reference(output, ref_idx),
+                    RowValueRef::KeyReference(key, ref_idx) => {
+                        self.write_key(output, key);
+                        self.dialect.write_reference(output, ref_idx);
+                        output.push('}');
+                    },
+                    _ => {}
+                }
+            },
+        }
+        self.dialect.end_row(output);
+    }
+}
+
+/// Writes the rows straight into the output of the parser
+impl RowSink for TextRowEncoder {
+    #[inline]
+    fn push_row(&mut self, row: ProtocolRowRef<'_>, output: &mut String) {
+        self.encode_row_ref(row, output);
+    }
+}
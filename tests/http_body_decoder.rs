@@ -0,0 +1,102 @@
+use serde_json::Value;
+#[cfg(feature = "compression")]
+use stream_protocol_lib::decoders::content_decoder::ContentEncoding;
+use stream_protocol_lib::{decoders::{chunked_transfer_decoder::ChunkedTransferDecoder, HttpBodyDecoder}, json_stream_parser::{parser_options::ParserOptions, parser_output::{parser_output_none::ParserOutputNone}, JsonStreamParser}, ref_index_generator::RefIndexGenerator, Shared};
+
+fn read_fixture(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+/// Feeds a recorded body through the decoder and the parser, in chunks of the given size
+fn parse_body(http_body_decoder: impl Fn() -> HttpBodyDecoder, body: &[u8]) -> Value {
+    let expected: Value = serde_json::from_slice(&read_fixture("http_body.json")).unwrap();
+    for chunk_size in [1, 2, 7, 64, body.len()] {
+        let mut decoder = http_body_decoder();
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+            RefIndexGenerator::new(),
+            0,
+            true,
+            ParserOptions::default(),
+            ParserOutputNone::new()
+        );
+        let mut decoded = vec![];
+        for chunk in body.chunks(chunk_size) {
+            decoded.clear();
+            decoder.decode(chunk, &mut decoded).unwrap();
+            json_stream_parser.feed(&decoded).unwrap();
+        }
+        decoded.clear();
+        decoder.finish(&mut decoded).unwrap();
+        json_stream_parser.feed(&decoded).unwrap();
+        assert!(json_stream_parser.finish_into(&mut String::new()).is_ok());
+        assert_eq!(json_stream_parser.get_buffered_data(), Some(&expected), "Chunk size {chunk_size}");
+    }
+    expected
+}
+
+#[test]
+fn test_chunked_body() {
+    parse_body(|| HttpBodyDecoder::new(true), &read_fixture("http_body_chunked.bin"));
+    parse_body(|| HttpBodyDecoder::new(false), &read_fixture("http_body.json"));
+}
+
+#[test]
+fn test_chunked_transfer_decoder_errors() {
+    // Cut before the last chunk
+    let mut decoder = ChunkedTransferDecoder::new();
+    let mut output = vec![];
+    decoder.decode(b"3\r\nabc\r\n", &mut output).unwrap();
+    assert_eq!(output, b"abc");
+    assert!(!decoder.is_done());
+    assert!(decoder.finish().is_err());
+
+    // Invalid framing
+    let mut decoder = ChunkedTransferDecoder::new();
+    assert!(decoder.decode(b"3\r\nabcd\r\n", &mut output).is_err());
+    let mut decoder = ChunkedTransferDecoder::new();
+    assert!(decoder.decode(b"x\r\n", &mut output).is_err());
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn test_compressed_body() {
+    parse_body(
+        || HttpBodyDecoder::new(true).with_content_encoding(ContentEncoding::Gzip),
+        &read_fixture("http_body_chunked_gzip.bin")
+    );
+    parse_body(
+        || HttpBodyDecoder::new(false).with_content_encoding(ContentEncoding::Deflate),
+        &read_fixture("http_body_deflate.bin")
+    );
+    parse_body(
+        || HttpBodyDecoder::new(false).with_content_encoding(ContentEncoding::from_header("deflate").unwrap()),
+        &read_fixture("http_body_raw_deflate.bin")
+    );
+
+    // Truncated compressed bodies
+    for (content_encoding, body) in [
+        (ContentEncoding::Deflate, read_fixture("http_body_deflate.bin")),
+        (ContentEncoding::Deflate, read_fixture("http_body_raw_deflate.bin")),
+        (ContentEncoding::Gzip, read_fixture("http_body_chunked_gzip.bin")),
+    ] {
+        let mut decoder = HttpBodyDecoder::new(content_encoding == ContentEncoding::Gzip).with_content_encoding(content_encoding);
+        let mut output = vec![];
+        let _ = decoder.decode(&body[..body.len() - 20], &mut output);
+        assert!(decoder.finish(&mut output).is_err(), "{content_encoding:?}");
+    }
+
+    // Complete chunked body, but carrying a truncated gzip payload
+    let mut gzip_payload = vec![];
+    let mut chunked_transfer_decoder = ChunkedTransferDecoder::new();
+    chunked_transfer_decoder.decode(&read_fixture("http_body_chunked_gzip.bin"), &mut gzip_payload).unwrap();
+    gzip_payload.truncate(gzip_payload.len() - 20);
+    let mut body = format!("{:x}\r\n", gzip_payload.len()).into_bytes();
+    body.extend_from_slice(&gzip_payload);
+    body.extend_from_slice(b"\r\n0\r\n\r\n");
+    let mut decoder = HttpBodyDecoder::new(true).with_content_encoding(ContentEncoding::Gzip);
+    let mut output = vec![];
+    decoder.decode(&body, &mut output).unwrap();
+    let err = decoder.finish(&mut output).unwrap_err();
+    assert!(err.msg.starts_with("Invalid compressed data"), "{}", err.msg);
+}
+
@@ -0,0 +1,98 @@
+use serde_json::{json, Value};
+use stream_protocol_lib::{decoders::input_encoding_decoder::{InputEncoding, InputEncodingDecoder}, json_stream_parser::{parser_options::ParserOptions, parser_output::{stream_protocol_output::StreamProtocolOutput}, JsonStreamParser}, ref_index_generator::RefIndexGenerator, Shared};
+
+fn utf16(input: &str, little_endian: bool, bom: bool) -> Vec<u8> {
+    let mut bytes = vec![];
+    if bom {
+        bytes.extend_from_slice(if little_endian { &[0xFF, 0xFE] } else { &[0xFE, 0xFF] });
+    }
+    for code_unit in input.encode_utf16() {
+        bytes.extend_from_slice(&if little_endian { code_unit.to_le_bytes() } else { code_unit.to_be_bytes() });
+    }
+    bytes
+}
+
+/// Decodes the input in chunks of the given size
+fn decode(encoding: InputEncoding, input: &[u8], chunk_size: usize) -> Vec<u8> {
+    let mut decoder = InputEncodingDecoder::new(encoding);
+    let mut decoded = vec![];
+    for chunk in input.chunks(chunk_size) {
+        let mut buffer = vec![];
+        decoded.extend_from_slice(decoder.decode(chunk, &mut buffer));
+    }
+    decoder.finish(&mut decoded);
+    decoded
+}
+
+#[test]
+fn test_input_encoding_decoder() {
+    let input = r#"{"a": "東京 🗼 é"}"#;
+    let tests = [
+        (InputEncoding::Utf8, input.as_bytes().to_vec()),
+        (InputEncoding::Utf8, [&[0xEF, 0xBB, 0xBF], input.as_bytes()].concat()),
+        (InputEncoding::Utf16Le, utf16(input, true, false)),
+        (InputEncoding::Utf16Le, utf16(input, true, true)),
+        (InputEncoding::Utf16Be, utf16(input, false, true)),
+        (InputEncoding::Auto, input.as_bytes().to_vec()),
+        (InputEncoding::Auto, [&[0xEF, 0xBB, 0xBF], input.as_bytes()].concat()),
+        (InputEncoding::Auto, utf16(input, true, true)),
+        (InputEncoding::Auto, utf16(input, false, true)),
+        (InputEncoding::Auto, utf16(input, true, false)),
+        (InputEncoding::Auto, utf16(input, false, false)),
+    ];
+    for (encoding, encoded) in tests {
+        for chunk_size in [1, 2, 3, 5, encoded.len()] {
+            assert_eq!(decode(encoding, &encoded, chunk_size), input.as_bytes(), "{encoding:?}, chunk size {chunk_size}");
+        }
+    }
+
+    // Latin-1
+    assert_eq!(decode(InputEncoding::Latin1, b"\"caf\xE9\"", 1), "\"café\"".as_bytes());
+
+    // Invalid UTF-16 : unpaired surrogates and odd trailing byte
+    let invalid = [&utf16("\"a", true, false)[..], &[0x3D, 0xD8], &utf16("b\"", true, false)[..], &[0x00]].concat();
+    assert_eq!(decode(InputEncoding::Utf16Le, &invalid, 1), "\"a\u{FFFD}b\"\u{FFFD}".as_bytes());
+
+    // Short inputs are decoded on finish
+    assert_eq!(decode(InputEncoding::Auto, b"1", 1), b"1");
+    assert_eq!(decode(InputEncoding::Utf8, &[0xEF], 1), &[0xEF]);
+}
+
+#[test]
+fn test_parser_input_encoding() {
+    let input = r#"{"a": ["東京", 1]}"#;
+    let tests = [
+        (InputEncoding::Utf8, [&[0xEF, 0xBB, 0xBF], input.as_bytes()].concat()),
+        (InputEncoding::Auto, utf16(input, true, true)),
+        (InputEncoding::Utf16Be, utf16(input, false, false)),
+    ];
+    for (encoding, encoded) in tests {
+        // Reference output, from the UTF-8 input
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+            RefIndexGenerator::new(),
+            0,
+            true,
+            ParserOptions::default(),
+            StreamProtocolOutput::new()
+        );
+        let expected_output = json_stream_parser.feed(input.as_bytes()).unwrap().unwrap();
+
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+            RefIndexGenerator::new(),
+            0,
+            true,
+            ParserOptions::default().with_input_encoding(encoding),
+            StreamProtocolOutput::new()
+        );
+        let mut output = String::new();
+        for byte in &encoded[..5] {
+            json_stream_parser.add_char_into(byte, &mut output).unwrap();
+        }
+        for chunk in encoded[5..].chunks(3) {
+            json_stream_parser.feed_into(chunk, &mut output).unwrap();
+        }
+        assert!(json_stream_parser.finish_into(&mut output).is_ok());
+        assert_eq!(output, expected_output, "{encoding:?}");
+        assert_eq!(json_stream_parser.get_buffered_data(), Some(&json!({"a": ["東京", 1]})));
+    }
+}
@@ -68,4 +68,32 @@ fn test_json_key_path_gemini() {
     assert!(json_key_path.move_down_object_or_array("text"));
 
     assert!(json_key_path.match_expr("*.candidates.*.content.parts.*.text", false));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_json_key_path_segments() {
+    let mut json_key_path = JsonKeyPath::new();
+    assert_eq!(json_key_path.segments().count(), 0);
+    assert_eq!(json_key_path.last_segment(), None);
+    assert_eq!(json_key_path.to_json_pointer(), "");
+
+    // Keys with dots, slashes and tildes keep their boundaries
+    assert!(json_key_path.move_down_object_or_array("a.b"));
+    assert!(json_key_path.move_down_object_or_array("0"));
+    assert!(json_key_path.move_down_object_or_array("c/d~e"));
+    assert_eq!(json_key_path.segments().collect::<Vec<_>>(), ["a.b", "0", "c/d~e"]);
+    assert_eq!(json_key_path.last_segment(), Some("c/d~e"));
+    assert_eq!(json_key_path.to_json_pointer(), "/a.b/0/c~1d~0e");
+
+    assert!(json_key_path.move_up());
+    assert_eq!(json_key_path.get_current_key(), "a.b.0");
+    assert!(json_key_path.move_up());
+    assert_eq!(json_key_path.get_current_key(), "a.b");
+    assert_eq!(json_key_path.last_segment(), Some("a.b"));
+
+    // An empty key is not moved down into
+    assert!(!json_key_path.move_down_object_or_array(""));
+    assert_eq!(json_key_path.to_json_pointer(), "/a.b");
+    assert!(json_key_path.move_up());
+    assert!(!json_key_path.move_up());
+}
@@ -341,4 +341,68 @@ fn test_json_chunk_iter() {
             assert_eq!(chunk.as_str(), format!("{}\n", result[chunk_idx]).as_str())
         }
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_json_chunk_iter_completion_markers() {
+    use serde_json::json;
+
+    let json_to_test = [
+        (
+            json!({"a": "b", "c": [1, {"d": null}, "efg"], "h": []}),
+            [
+                r#"0={}"#,
+                r#"0+={"a":"$ke$1"}"#,
+                r#"1="b""#,
+                r#"1!"#,
+                r#"0+={"c":"$ke$2"}"#,
+                r#"2=[]"#,
+                r#"2+="$ke$3""#,
+                r#"3=1"#,
+                r#"3!"#,
+                r#"2+="$ke$4""#,
+                r#"4={}"#,
+                r#"4+={"d":"$ke$5"}"#,
+                r#"5=null"#,
+                r#"5!"#,
+                r#"4!"#,
+                r#"2+="$ke$6""#,
+                r#"6="ef""#,
+                r#"6+="g""#,
+                r#"6!"#,
+                r#"2!"#,
+                r#"0+={"h":"$ke$7"}"#,
+                r#"7=[]"#,
+                r#"7!"#,
+                r#"0!"#,
+            ].to_vec()
+        ),
+        (json!("a"), [r#"0="a""#, r#"0!"#].to_vec()),
+        (json!("abc"), [r#"0="ab""#, r#"0+="c""#, r#"0!"#].to_vec()),
+        (json!(1), [r#"0=1"#, r#"0!"#].to_vec()),
+        (json!([]), [r#"0=[]"#, r#"0!"#].to_vec()),
+        (json!([true]), [r#"0=[]"#, r#"0+="$ke$1""#, r#"1=true"#, r#"1!"#, r#"0!"#].to_vec()),
+    ];
+    for (to_test, result) in json_to_test {
+        let idx_generator = RefIndexGenerator::new();
+        let chunker = JsonProtocolChunker::new(to_test, idx_generator, 0).with_completion_markers();
+        let chunks = chunker.chunks(2).collect::<Vec<String>>();
+        assert_eq!(chunks, result.iter().map(|row| format!("{}\n", row)).collect::<Vec<String>>());
+    }
+}
+
+#[test]
+fn test_json_chunk_iter_end_row() {
+    use serde_json::json;
+
+    let idx_generator = RefIndexGenerator::new();
+    let chunker = JsonProtocolChunker::new(json!({"a": [1]}), idx_generator, 0).with_end_row();
+    let chunks = chunker.chunks(2).collect::<Vec<String>>();
+    assert_eq!(chunks, [
+        "0={}\n",
+        "0+={\"a\":\"$ke$1\"}\n",
+        "1=[]\n",
+        "1+=\"$ke$2\"\n",
+        "2=1\n",
+        "!end\n",
+    ]);
+}
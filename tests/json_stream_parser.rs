@@ -1,8 +1,8 @@
 use serde_json::{json, Value};
 use test_log::test;
-use std::{cell::RefCell, rc::Rc, str::FromStr};
+use std::{cell::RefCell, str::FromStr, sync::{atomic::{AtomicUsize, Ordering}, Mutex}, time::Duration};
 
-use stream_protocol_lib::{json_stream_parser::{parser_options::ParserOptions, parser_output::{stream_protocol_output::StreamProtocolOutput, ParserOutputTrait}, JsonStreamParser, ParserEvent}, ref_index_generator::RefIndexGenerator};
+use stream_protocol_lib::{json_key_path::JsonKeyPath, json_stream_parser::{error::Truncation, parser_options::{Clock, FlushPolicy, ParserOptions}, parser_output::{closure_output::ClosureOutput, json_patch_output::{JsonPatchOutput, StringGrowth}, path_protocol_output::{PathProtocolDecoder, PathProtocolOutput}, stream_protocol_output::StreamProtocolOutput, tee_output::TeeOutput, ParentNode, ParserOutputTrait, ValueKind}, parse_partial, JsonStreamParser, ParserEvent}, ref_index_generator::RefIndexGenerator, stream_protocol::{dialect::ProtocolDialect, protocol_decoder::ProtocolDecoder}, Shared};
 
 #[test]
 fn test_unit() {
@@ -270,7 +270,7 @@ fn test_unit() {
         ref_index_generator.generate(); // 1 : generate once to simulate being in the middle
         let cnt = ref_index_generator.generate(); // 2
         
-        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Rc<Value>>)>, _> = JsonStreamParser::new(
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
             ref_index_generator,
             cnt,
             true,
@@ -313,7 +313,7 @@ fn test_flush_regular() {
     ref_index_generator.generate(); // 1 : generate once to simulate being in the middle
     let cnt = ref_index_generator.generate(); // 2
     
-    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Rc<Value>>)>, _> = JsonStreamParser::new(
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
         ref_index_generator,
         cnt,
         false,
@@ -361,7 +361,7 @@ fn test_flush_inbetween_utf8_boundaries() {
     ref_index_generator.generate(); // 1 : generate once to simulate being in the middle
     let cnt = ref_index_generator.generate(); // 2
     
-    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Rc<Value>>)>, _> = JsonStreamParser::new(
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
         ref_index_generator,
         cnt,
         false,
@@ -412,7 +412,7 @@ fn test_flush_for_object_keys() {
     ref_index_generator.generate(); // 1 : generate once to simulate being in the middle
     let cnt = ref_index_generator.generate(); // 2
     
-    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Rc<Value>>)>, _> = JsonStreamParser::new(
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
         ref_index_generator,
         cnt,
         false,
@@ -462,7 +462,7 @@ fn test_gpt() {
     ref_index_generator.generate(); // 1 : generate once to simulate being in the middle
     let cnt = ref_index_generator.generate(); // 2
 
-    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Rc<Value>>)>, _> = JsonStreamParser::new(
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
         ref_index_generator,
         cnt,
         false,
@@ -471,21 +471,21 @@ fn test_gpt() {
     );
 
     // Testing events
-    json_stream_parser.add_event_handler(ParserEvent::OnElementEnd, "references.0".to_string(), Box::new(|value: Option<Rc<Value>>| {
+    json_stream_parser.add_event_handler(ParserEvent::OnElementEnd, "references.0".to_string(), Box::new(|value: Option<Shared<Value>>| {
         assert!(value.is_some());
         let value = value.unwrap(); 
         assert!(value.is_string());
         let value = value.as_str().unwrap();
         assert_eq!("source_1", value);
     }));
-    json_stream_parser.add_event_handler(ParserEvent::OnElementEnd, "references.*".to_string(), Box::new(|value: Option<Rc<Value>>| {
+    json_stream_parser.add_event_handler(ParserEvent::OnElementEnd, "references.*".to_string(), Box::new(|value: Option<Shared<Value>>| {
         assert!(value.is_some());
         let value = value.unwrap(); 
         assert!(value.is_string());
         let value = value.as_str().unwrap();
         assert!(value == "source_1" || value == "source_2"); // Any of the array due to the wildcard
     }));
-    json_stream_parser.add_event_handler(ParserEvent::OnElementEnd, "test_escape".to_string(), Box::new(|value: Option<Rc<Value>>| {
+    json_stream_parser.add_event_handler(ParserEvent::OnElementEnd, "test_escape".to_string(), Box::new(|value: Option<Shared<Value>>| {
         assert!(value.is_some());
         let value = value.unwrap(); 
         assert!(value.is_string());
@@ -622,7 +622,7 @@ fn test_gemini() {
         ParserOptions::default(),
         StreamProtocolOutput::new()
     )
-        .with_event_handler(ParserEvent::OnElementEnd, "*.candidates.*.content.parts.*.text".to_string(), Box::new(move |value: Option<Rc<Value>>| {
+        .with_event_handler(ParserEvent::OnElementEnd, "*.candidates.*.content.parts.*.text".to_string(), Box::new(move |value: Option<Shared<Value>>| {
             assert!(value.is_some());
             let value = value.unwrap(); 
             assert!(value.is_string());
@@ -666,7 +666,7 @@ fn test_flush_buffer() {
     let input = input.replace("\n", ""); // Use spaces previously for readability, but remove now to make a valid JSON
     let ref_index_generator = RefIndexGenerator::new();
 
-    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Rc<Value>>)>, _> = JsonStreamParser::new(
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
         ref_index_generator,
         0,
         true,
@@ -797,7 +797,7 @@ fn test_filter_output() {
         )
     ] {
         let ref_index_generator = RefIndexGenerator::new();
-        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Rc<Value>>)>, _> = JsonStreamParser::new(
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
             ref_index_generator,
             0,
             true,
@@ -944,7 +944,7 @@ fn test_filter_buffer() {
         )
     ] {
         let ref_index_generator = RefIndexGenerator::new();
-        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Rc<Value>>)>, _> = JsonStreamParser::new(
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
             ref_index_generator,
             0,
             true,
@@ -1142,7 +1142,7 @@ fn test_filters_both() {
         ),
     ] {
         let ref_index_generator = RefIndexGenerator::new();
-        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Rc<Value>>)>, _> = JsonStreamParser::new(
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
             ref_index_generator,
             0,
             true,
@@ -1268,7 +1268,7 @@ fn test_edge_cases() {
     ] {
         let ref_index_generator = RefIndexGenerator::new();
     
-        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Rc<Value>>)>, _> = JsonStreamParser::new(
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
             ref_index_generator,
             0,
             true,
@@ -1294,7 +1294,13 @@ fn test_edge_cases() {
                 }
             }
         }
-        json_stream_parser.finish();
+        let mut output = String::new();
+        assert!(json_stream_parser.finish_into(&mut output).is_ok());
+        // A root number is only complete once finished
+        for output_row in output.lines() {
+            assert_eq!(expected_line_arr[line_counter], output_row);
+            line_counter += 1;
+        }
     
         // Testing buffered data
         let buffered_data = json_stream_parser.get_buffered_data();
@@ -1342,7 +1348,7 @@ fn test_gemini2() {
       }"#;
     let ref_index_generator = RefIndexGenerator::new();
 
-    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Rc<Value>>)>, _> = JsonStreamParser::new(
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
         ref_index_generator,
         0,
         true,
@@ -1361,4 +1367,852 @@ fn test_gemini2() {
     let buffered_data = json_stream_parser.get_buffered_data();
     assert!(buffered_data.is_some());
     //println!("{}", buffered_data.unwrap()); // For debugging
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_fork() {
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator.clone(),
+        0,
+        true,
+        ParserOptions::default(),
+        StreamProtocolOutput::new()
+    );
+    let mut output = String::new();
+    for byte in r#"{"a":"b","#.as_bytes() {
+        if let Some(row) = json_stream_parser.add_char(byte).unwrap() {
+            output.push_str(&row);
+        }
+    }
+    assert_eq!(output, "0={}\n0+={\"a\":\"$ke$2\"}\n2=\"\"\n2+=\"b\"\n");
+
+    // A failed speculation is simply dropped
+    let mut fork = json_stream_parser.fork();
+    assert!(fork.add_char(&b'"').is_ok());
+    assert!(fork.add_char(&b'c').is_ok());
+    assert!(fork.add_char(&b'"').is_ok());
+    assert!(fork.add_char(&b':').is_ok());
+    assert!(fork.add_char(&b']').is_err());
+    drop(fork);
+    assert_eq!(ref_index_generator.generate(), 3); // The fork did not consume indices of the shared generator
+    assert_eq!(json_stream_parser.get_buffered_data(), Some(&json!({"a": "b"})));
+
+    // A successful speculation is committed back
+    let mut fork = json_stream_parser.fork();
+    let mut fork_output = String::new();
+    for byte in r#""c":1}"#.as_bytes() {
+        if let Some(row) = fork.add_char(byte).unwrap() {
+            fork_output.push_str(&row);
+        }
+    }
+    assert_eq!(fork_output, "0+={\"c\":1}\n");
+    assert_eq!(json_stream_parser.get_buffered_data(), Some(&json!({"a": "b"})));
+    json_stream_parser.commit(fork).unwrap();
+    assert_eq!(json_stream_parser.get_buffered_data(), Some(&json!({"a": "b", "c": 1})));
+    assert_eq!(ref_index_generator.generate(), 6); // Indices 4 and 5 were used by the fork for the key "c" and its value
+}
+
+#[test]
+fn test_fork_events_and_conflicts() {
+    let ref_index_generator = RefIndexGenerator::new();
+    let received = Shared::new(Mutex::new(Vec::new()));
+    let received_clone = Shared::clone(&received);
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator.clone(),
+        0,
+        false,
+        ParserOptions::default(),
+        StreamProtocolOutput::new()
+    ).with_event_handler(ParserEvent::OnElementEnd, "*".to_string(), Box::new(move |value| {
+        received_clone.lock().unwrap().push(value.map(|value| value.as_ref().clone()));
+    }));
+    json_stream_parser.feed(br#"["a", "#).unwrap();
+    assert_eq!(*received.lock().unwrap(), vec![Some(json!("a"))]);
+
+    // Events of the fork are handled once committed only
+    let mut fork = json_stream_parser.fork();
+    fork.feed(br#""b", 1"#).unwrap();
+    assert_eq!(received.lock().unwrap().len(), 1);
+    json_stream_parser.commit(fork).unwrap();
+    assert_eq!(*received.lock().unwrap(), vec![Some(json!("a")), Some(json!("b"))]);
+
+    // Indices generated on both sides would clash : the commit is refused, leaving the parser untouched
+    let mut fork = json_stream_parser.fork();
+    fork.feed(br#", "c""#).unwrap();
+    ref_index_generator.generate();
+    assert!(json_stream_parser.commit(fork).is_err());
+    assert_eq!(json_stream_parser.feed(b"]").unwrap(), Some("0+=1\n".to_string()));
+    assert_eq!(*received.lock().unwrap(), vec![Some(json!("a")), Some(json!("b")), Some(json!(1))]);
+
+    // A fork which generated no index can always be committed
+    let mut fork = json_stream_parser.fork();
+    ref_index_generator.generate();
+    assert!(fork.finish_into(&mut String::new()).is_ok());
+    assert!(json_stream_parser.commit(fork).is_ok());
+}
+
+#[test]
+fn test_feed() {
+    let input = r#"{"list": [{"text": "Some \"quoted\" text\\with escapes 東京", "n": 12.5}, "東京都", true, null], "key with \n newline": "end"}"#;
+
+    // Reference output, byte by byte
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        true,
+        ParserOptions::default(),
+        StreamProtocolOutput::new()
+    );
+    let mut expected_output = String::new();
+    for byte in input.as_bytes() {
+        if let Some(row) = json_stream_parser.add_char(byte).unwrap() {
+            expected_output.push_str(&row);
+        }
+    }
+    let expected_buffer = json_stream_parser.take_buffered_data().unwrap();
+    assert_eq!(expected_buffer, serde_json::from_str::<Value>(input).unwrap());
+
+    // Feeding chunks of any size must give the same result
+    for chunk_size in [1, 2, 3, 7, 16, input.len()] {
+        let ref_index_generator = RefIndexGenerator::new();
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+            ref_index_generator,
+            0,
+            true,
+            ParserOptions::default(),
+            StreamProtocolOutput::new()
+        );
+        let mut output = String::new();
+        for chunk in input.as_bytes().chunks(chunk_size) {
+            if let Some(rows) = json_stream_parser.feed(chunk).unwrap() {
+                output.push_str(&rows);
+            }
+        }
+        assert_eq!(output, expected_output, "Chunk size {chunk_size}");
+        assert_eq!(json_stream_parser.take_buffered_data().unwrap(), expected_buffer);
+    }
+
+    // Errors are reported the same way
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        false,
+        ParserOptions::default(),
+        StreamProtocolOutput::new()
+    );
+    assert!(json_stream_parser.feed(br#"{"a": "b\x"}"#).is_err());
+}
+
+#[test]
+fn test_write_into() {
+    let input = r#"{"a": [1, "b", {"c\"": null}], "d": "e"}"#;
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        false,
+        ParserOptions::default(),
+        StreamProtocolOutput::new()
+    );
+
+    // The same buffer is reused across calls, and rows are appended to it
+    let mut output = String::with_capacity(1024);
+    let (first_half, second_half) = input.as_bytes().split_at(input.len() / 2);
+    for byte in first_half {
+        json_stream_parser.add_char_into(byte, &mut output).unwrap();
+    }
+    json_stream_parser.flush_into(&mut output);
+    json_stream_parser.feed_into(second_half, &mut output).unwrap();
+    let expected = concat!(
+        "0={}\n",
+        "0+={\"a\":\"$ke$2\"}\n",
+        "2=[]\n",
+        "2+=1\n",
+        "2+=\"$ke$4\"\n",
+        "4=\"\"\n",
+        "4+=\"b\"\n",
+        "2+=\"$ke$5\"\n",
+        "5={}\n",
+        "5+={\"c\\\"\":null}\n",
+        "0+={\"d\":\"$ke$9\"}\n",
+        "9=\"\"\n",
+        "9+=\"e\"\n",
+    );
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn test_flush_grapheme_safe() {
+    // Flag pair, ZWJ family sequence, and a combining accent
+    let input = "\"a🇯🇵b👨\u{200d}👩\u{200d}👧e\u{301}\"";
+    let expected_line_arr = [
+        r#"2="""#,
+        r#"2+="a""#,
+        r#"2+="🇯🇵""#,
+        r#"2+="b""#,
+        "2+=\"👨\u{200d}👩\u{200d}👧\"",
+        "2+=\"e\u{301}\"",
+    ];
+
+    let ref_index_generator = RefIndexGenerator::new();
+    ref_index_generator.generate(); // 1 : generate once to simulate being in the middle
+    let cnt = ref_index_generator.generate(); // 2
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        cnt,
+        true,
+        ParserOptions::default().with_grapheme_safe_flush(true),
+        StreamProtocolOutput::new()
+    );
+
+    // Flush after every byte : no row may contain a partial grapheme
+    let mut lines = vec![];
+    for byte in input.as_bytes() {
+        if let Some(output) = json_stream_parser.add_char(byte).unwrap() {
+            lines.push(output.trim().to_string());
+        }
+        if let Some(output) = json_stream_parser.flush() {
+            lines.push(output.trim().to_string());
+        }
+    }
+    assert_eq!(lines, expected_line_arr);
+    assert_eq!(json_stream_parser.get_buffered_data(), Some(&json!("a🇯🇵b👨\u{200d}👩\u{200d}👧e\u{301}")));
+}
+
+#[test]
+fn test_flush_policy() {
+    let input = r#"{"text": "Hello big world. 東京です。OK? Yes", "n": 1}"#;
+    let cases = [
+        (
+            FlushPolicy::WordBoundary,
+            vec![r#"2+="Hello ""#, r#"2+="big ""#, r#"2+="world. ""#, r#"2+="東京です。OK? ""#, r#"2+="Yes""#],
+        ),
+        (
+            FlushPolicy::SentenceBoundary,
+            vec![r#"2+="Hello big world. ""#, r#"2+="東京です。""#, r#"2+="OK? ""#, r#"2+="Yes""#],
+        ),
+        (
+            FlushPolicy::MaxBufferedBytes(8),
+            vec![r#"2+="Hello bi""#, r#"2+="g world.""#, r#"2+=" 東京""#, r#"2+="です""#, r#"2+="。OK? Y""#, r#"2+="es""#],
+        ),
+    ];
+
+    for (flush_policy, expected_string_rows) in cases {
+        // Byte by byte
+        let ref_index_generator = RefIndexGenerator::new();
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+            ref_index_generator,
+            0,
+            false,
+            ParserOptions::default().with_flush_policy(flush_policy.clone()),
+            StreamProtocolOutput::new()
+        );
+        let mut expected_output = String::new();
+        for byte in input.as_bytes() {
+            json_stream_parser.add_char_into(byte, &mut expected_output).unwrap();
+        }
+        let string_rows: Vec<&str> = expected_output.lines().filter(|line| line.starts_with("2+=")).collect();
+        assert_eq!(string_rows, expected_string_rows);
+
+        // Feeding in chunks must flush at the same places
+        for chunk_size in [1, 3, 5, input.len()] {
+            let ref_index_generator = RefIndexGenerator::new();
+            let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+                ref_index_generator,
+                0,
+                false,
+                ParserOptions::default().with_flush_policy(flush_policy.clone()),
+                StreamProtocolOutput::new()
+            );
+            let mut output = String::new();
+            for chunk in input.as_bytes().chunks(chunk_size) {
+                json_stream_parser.feed_into(chunk, &mut output).unwrap();
+            }
+            assert_eq!(output, expected_output, "Chunk size {chunk_size}");
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ManualClock {
+    now: Mutex<Duration>,
+}
+
+impl ManualClock {
+    fn set(&self, now: Duration) {
+        *self.now.lock().unwrap() = now;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[test]
+fn test_flush_policy_interval() {
+    let clock = Shared::new(ManualClock::default());
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        2,
+        false,
+        ParserOptions::default().with_flush_policy(FlushPolicy::Interval {
+            interval: Duration::from_millis(100),
+            clock: clock.clone(),
+        }),
+        StreamProtocolOutput::new()
+    );
+
+    let mut output = String::new();
+    json_stream_parser.feed_into(br#""abc"#, &mut output).unwrap();
+    assert_eq!(output, "2=\"\"\n");
+
+    // Not enough time has passed
+    clock.set(Duration::from_millis(50));
+    json_stream_parser.feed_into(b"d", &mut output).unwrap();
+    assert_eq!(output, "2=\"\"\n");
+
+    // Bytes have been waiting long enough
+    clock.set(Duration::from_millis(120));
+    json_stream_parser.feed_into(b"e", &mut output).unwrap();
+    assert_eq!(output, "2=\"\"\n2+=\"abcde\"\n");
+
+    // The interval starts again from the next unflushed bytes
+    clock.set(Duration::from_millis(200));
+    json_stream_parser.feed_into(b"f", &mut output).unwrap();
+    clock.set(Duration::from_millis(290));
+    json_stream_parser.feed_into(b"g\"", &mut output).unwrap();
+    assert_eq!(output, "2=\"\"\n2+=\"abcde\"\n2+=\"fg\"\n");
+}
+
+#[test]
+fn test_poll_flush() {
+    let clock = Shared::new(ManualClock::default());
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        false,
+        ParserOptions::default().with_flush_policy(FlushPolicy::Interval {
+            interval: Duration::from_millis(100),
+            clock: clock.clone(),
+        }),
+        StreamProtocolOutput::new()
+    );
+
+    let mut output = String::new();
+    json_stream_parser.feed_into(br#"{"a": "abc"#, &mut output).unwrap();
+    assert_eq!(output, "0={}\n0+={\"a\":\"$ke$2\"}\n2=\"\"\n");
+    output.clear();
+
+    // No byte arrives, but polling before the interval has passed does not flush
+    clock.set(Duration::from_millis(50));
+    json_stream_parser.poll_flush_into(&mut output);
+    assert_eq!(output, "");
+
+    // Once the interval has passed, polling alone flushes the waiting bytes
+    clock.set(Duration::from_millis(100));
+    assert_eq!(json_stream_parser.poll_flush(), Some("2+=\"abc\"\n".to_string()));
+    clock.set(Duration::from_millis(300));
+    assert_eq!(json_stream_parser.poll_flush(), None);
+
+    // Nothing to flush outside of a string
+    json_stream_parser.feed_into(br#"d", "b": 1"#, &mut output).unwrap();
+    output.clear();
+    clock.set(Duration::from_millis(500));
+    json_stream_parser.poll_flush_into(&mut output);
+    assert_eq!(output, "");
+}
+
+#[test]
+fn test_finish_truncations() {
+    let tests = [
+        (r#"{"a": [1, {"b": "c"}]}"#, vec![]),
+        ("12", vec![]),
+        ("", vec![Truncation::EmptyDocument]),
+        (r#"{"a": [1, {"b": "cut"#, vec![
+            Truncation::UnterminatedString { key_path: "a.1.b".to_string(), object_key: false },
+            Truncation::OpenObject { key_path: "a.1".to_string() },
+            Truncation::OpenArray { key_path: "a".to_string() },
+            Truncation::OpenObject { key_path: "".to_string() },
+        ]),
+        (r#"{"a": {"ke"#, vec![
+            Truncation::UnterminatedString { key_path: "a".to_string(), object_key: true },
+            Truncation::OpenObject { key_path: "a".to_string() },
+            Truncation::OpenObject { key_path: "".to_string() },
+        ]),
+        (r#"{"a": {"key": "#, vec![
+            Truncation::DanglingKey { key_path: "a".to_string(), key: "key".to_string() },
+            Truncation::OpenObject { key_path: "a".to_string() },
+            Truncation::OpenObject { key_path: "".to_string() },
+        ]),
+        (r#"[tr"#, vec![
+            Truncation::UnterminatedLiteral { key_path: "0".to_string() },
+            Truncation::OpenArray { key_path: "".to_string() },
+        ]),
+        (r#"[[], 3"#, vec![
+            Truncation::OpenArray { key_path: "".to_string() },
+        ]),
+        ("1.", vec![
+            Truncation::InvalidNumber { key_path: "".to_string(), msg: "Error parsing serde number on finish : 1.".to_string() },
+        ]),
+    ];
+
+    for (input, expected_truncations) in tests {
+        let ref_index_generator = RefIndexGenerator::new();
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+            ref_index_generator,
+            0,
+            true,
+            ParserOptions::default(),
+            StreamProtocolOutput::new()
+        );
+        json_stream_parser.feed(input.as_bytes()).unwrap();
+        let result = json_stream_parser.finish_into(&mut String::new());
+        if expected_truncations.is_empty() {
+            assert!(result.is_ok(), "{input} : {result:?}");
+        } else {
+            assert_eq!(result.unwrap_err().truncations, expected_truncations, "{input}");
+        }
+    }
+}
+
+#[test]
+fn test_parse_partial() {
+    let tests = [
+        ("", Value::Null),
+        ("12", json!(12)),
+        ("-", Value::Null),
+        (r#""Hel"#, json!("Hel")),
+        (r#"{"a": "Hello wo"#, json!({"a": "Hello wo"})),
+        (r#"{"a": [1, 2.5e"#, json!({"a": [1, 2.5]})),
+        (r#"{"a": [1, {"b": tr"#, json!({"a": [1, {"b": null}]})),
+        (r#"{"a": 1, "b"#, json!({"a": 1})),
+        (r#"{"a": 1, "b": "#, json!({"a": 1})),
+        (r#"{"a": "東京é"#, json!({"a": "東京é"})),
+        (r#"{"a": {"b": [], "c": 1}}"#, json!({"a": {"b": [], "c": 1}})),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(parse_partial(input).unwrap(), expected, "{input}");
+    }
+    assert!(parse_partial(r#"{"a": x"#).is_err());
+}
+
+#[test]
+fn test_current_partial_value() {
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        true,
+        ParserOptions::default(),
+        StreamProtocolOutput::new()
+    );
+    assert_eq!(json_stream_parser.current_partial_value(), None);
+
+    // Flushed parts and the string in progress are both included, cutting a multibyte character
+    let input = r#"{"text": "Some 東京"#.as_bytes();
+    json_stream_parser.feed(&input[..input.len() - 1]).unwrap();
+    assert_eq!(json_stream_parser.current_partial_value(), Some(json!({"text": "Some 東"})));
+    json_stream_parser.flush();
+    json_stream_parser.feed(&input[input.len() - 1..]).unwrap();
+    assert_eq!(json_stream_parser.current_partial_value(), Some(json!({"text": "Some 東京"})));
+
+    // The completed data is not affected
+    assert_eq!(json_stream_parser.get_buffered_data(), Some(&json!({"text": null})));
+}
+
+#[test]
+fn test_recovery() {
+    let tests = [
+        // Invalid escapes
+        (r#"{"a": "b\xc"}"#, json!({"a": "b\u{FFFD}c"}), vec![9]),
+        (r#"["\u12G4"]"#, json!(["\u{FFFD}G4"]), vec![6]),
+        (r#"["\ud83d!"]"#, json!(["\u{FFFD}!"]), vec![7]),
+        // Malformed elements
+        (r#"{"a": x, "b": 1}"#, json!({"b": 1}), vec![6]),
+        (r#"{"a": tru}"#, json!({}), vec![9]),
+        (r#"[1, nul, {"c": [2, "]"]}, 3]"#, json!([1, {"c": [2, "]"]}, 3]), vec![7]),
+        (r#"[1, x{"c": [2, "]"]}, 3]"#, json!([1, 3]), vec![4]),
+        (r#"[1,, 2]"#, json!([1, 2]), vec![3]),
+        (r#"[1, 2 } ,3]"#, json!([1, 2, 3]), vec![6]),
+        (r#"{"a": x], "b": 1}"#, json!({"b": 1}), vec![6, 7]),
+        (r#"{"a": [1.2.3], "b": true}"#, json!({"a": [], "b": true}), vec![12]),
+        // Text around the JSON
+        (r#"Sure! {"a": 1}"#, json!({"a": 1}), vec![0]),
+    ];
+
+    for (input, expected, expected_positions) in tests {
+        let ref_index_generator = RefIndexGenerator::new();
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+            ref_index_generator,
+            0,
+            true,
+            ParserOptions::default().with_recovery(true),
+            StreamProtocolOutput::new()
+        );
+        let mut output = String::new();
+        json_stream_parser.feed_into(input.as_bytes(), &mut output).unwrap();
+        assert!(json_stream_parser.finish_into(&mut output).is_ok(), "{input}");
+        assert_eq!(json_stream_parser.get_buffered_data(), Some(&expected), "{input}");
+        let positions: Vec<usize> = json_stream_parser.warnings().iter().map(|warning| warning.position).collect();
+        assert_eq!(positions, expected_positions, "{input}");
+
+        // The protocol output stays consistent with the buffered data
+        assert_eq!(protocol_output_to_value(&output), expected, "{input}");
+    }
+
+    // Invalid UTF-8, including across flushes
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        true,
+        ParserOptions::default().with_recovery(true),
+        StreamProtocolOutput::new()
+    );
+    let mut output = String::new();
+    json_stream_parser.feed_into(b"[\"a\xFFb", &mut output).unwrap();
+    json_stream_parser.flush_into(&mut output);
+    json_stream_parser.feed_into(b"c\xE6\x9D\"]", &mut output).unwrap();
+    assert_eq!(json_stream_parser.get_buffered_data(), Some(&json!(["a\u{FFFD}bc\u{FFFD}"])));
+    assert_eq!(json_stream_parser.take_warnings().len(), 2);
+    assert!(json_stream_parser.warnings().is_empty());
+
+    // Without recovery, errors are still returned
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        false,
+        ParserOptions::default(),
+        StreamProtocolOutput::new()
+    );
+    assert!(json_stream_parser.feed(br#"{"a": x}"#).is_err());
+}
+
+/// Minimal client side interpretation of the stream protocol rows, resolving references
+fn protocol_output_to_value(output: &str) -> Value {
+    let mut decoder = ProtocolDecoder::new(ProtocolDialect::default());
+    decoder.apply_rows(output).unwrap();
+    decoder.value().unwrap()
+}
+
+#[test]
+fn test_completion_markers() {
+    let tests = [
+        (
+            r#"{"a": "b", "c": [1, {"d": null}, "e"], "f": {"g": 2}, "h": []}"#,
+            vec![
+                r#"0={}"#,
+                r#"0+={"a":"$ke$2"}"#,
+                r#"2="""#,
+                r#"2+="b""#,
+                r#"2!"#,
+                r#"0+={"c":"$ke$4"}"#,
+                r#"4=[]"#,
+                r#"4+=1"#,
+                r#"4+="$ke$6""#,
+                r#"6={}"#,
+                r#"6+={"d":null}"#,
+                r#"6!"#,
+                r#"4+="$ke$9""#,
+                r#"9="""#,
+                r#"9+="e""#,
+                r#"9!"#,
+                r#"4!"#,
+                r#"0+={"f":"$ke$11"}"#,
+                r#"11={}"#,
+                r#"11+={"g":2}"#,
+                r#"11!"#,
+                r#"0+={"h":"$ke$15"}"#,
+                r#"15=[]"#,
+                r#"15!"#,
+                r#"0!"#,
+            ]
+        ),
+        (r#"[1, [2]]"#, vec![r#"0=[]"#, r#"0+=1"#, r#"0+="$ke$2""#, r#"2=[]"#, r#"2+=2"#, r#"2!"#, r#"0!"#]),
+        (r#""abc""#, vec![r#"0="""#, r#"0+="abc""#, r#"0!"#]),
+        (r#"true"#, vec![r#"0=true"#, r#"0!"#]),
+    ];
+    for (input, expected_rows) in tests {
+        let ref_index_generator = RefIndexGenerator::new();
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+            ref_index_generator,
+            0,
+            false,
+            ParserOptions::default(),
+            StreamProtocolOutput::new().with_completion_markers()
+        );
+        let output = json_stream_parser.feed(input.as_bytes()).unwrap().unwrap_or_default();
+        assert_eq!(output.lines().collect::<Vec<_>>(), expected_rows, "{input}");
+    }
+}
+
+#[test]
+fn test_end_rows() {
+    let tests = [
+        (r#"{"a": 1}"#, vec![r#"0={}"#, r#"0+={"a":1}"#, r#"0!"#, r#"!end"#]),
+        ("12", vec![r#"0=12"#, r#"0!"#, r#"!end"#]),
+        (r#"{"a": x}"#, vec![r#"0={}"#, r#"!error={"message":"Object does not have a valid value"}"#]),
+        (r#"{"a": "b"#, vec![
+            r#"0={}"#,
+            r#"0+={"a":"$ke$2"}"#,
+            r#"2="""#,
+            r#"!error={"message":"FinishError: document is incomplete\n - unterminated string at 'a'\n - unclosed object at ''"}"#
+        ]),
+    ];
+    for (input, expected_rows) in tests {
+        let ref_index_generator = RefIndexGenerator::new();
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+            ref_index_generator,
+            0,
+            false,
+            ParserOptions::default(),
+            StreamProtocolOutput::new().with_completion_markers().with_end_rows()
+        );
+        let mut output = String::new();
+        if json_stream_parser.feed_into(input.as_bytes(), &mut output).is_ok() {
+            let _ = json_stream_parser.finish_into(&mut output);
+        }
+        assert_eq!(output.lines().collect::<Vec<_>>(), expected_rows, "{input}");
+    }
+}
+
+#[test]
+fn test_json_patch_output() {
+    let input = r#"{"choices": [{"text": "Hello", "n": 1}], "a/b": [true, null]}"#;
+    let tests = [
+        (StringGrowth::Append, vec![
+            r#"{"op":"add","path":"","value":{}}"#,
+            r#"{"op":"add","path":"/choices","value":[]}"#,
+            r#"{"op":"add","path":"/choices/0","value":{}}"#,
+            r#"{"op":"add","path":"/choices/0/text","value":""}"#,
+            r#"{"op":"append","path":"/choices/0/text","value":"Hel"}"#,
+            r#"{"op":"append","path":"/choices/0/text","value":"lo"}"#,
+            r#"{"op":"add","path":"/choices/0/n","value":1}"#,
+            r#"{"op":"add","path":"/a~1b","value":[]}"#,
+            r#"{"op":"add","path":"/a~1b/0","value":true}"#,
+            r#"{"op":"add","path":"/a~1b/1","value":null}"#,
+        ]),
+        (StringGrowth::Replace, vec![
+            r#"{"op":"add","path":"","value":{}}"#,
+            r#"{"op":"add","path":"/choices","value":[]}"#,
+            r#"{"op":"add","path":"/choices/0","value":{}}"#,
+            r#"{"op":"add","path":"/choices/0/text","value":""}"#,
+            r#"{"op":"replace","path":"/choices/0/text","value":"Hel"}"#,
+            r#"{"op":"replace","path":"/choices/0/text","value":"Hello"}"#,
+            r#"{"op":"add","path":"/choices/0/n","value":1}"#,
+            r#"{"op":"add","path":"/a~1b","value":[]}"#,
+            r#"{"op":"add","path":"/a~1b/0","value":true}"#,
+            r#"{"op":"add","path":"/a~1b/1","value":null}"#,
+        ]),
+    ];
+    for (string_growth, expected_operations) in tests {
+        let ref_index_generator = RefIndexGenerator::new();
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+            ref_index_generator,
+            0,
+            false,
+            ParserOptions::default(),
+            JsonPatchOutput::new().with_string_growth(string_growth)
+        );
+        let split_position = input.find("lo").unwrap();
+        let mut output = String::new();
+        json_stream_parser.feed_into(input[..split_position].as_bytes(), &mut output).unwrap();
+        json_stream_parser.flush_into(&mut output);
+        json_stream_parser.feed_into(input[split_position..].as_bytes(), &mut output).unwrap();
+        json_stream_parser.finish_into(&mut output).unwrap();
+        assert_eq!(output.lines().collect::<Vec<_>>(), expected_operations);
+    }
+
+    // A root basic value is added once complete
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        RefIndexGenerator::new(),
+        0,
+        false,
+        ParserOptions::default(),
+        JsonPatchOutput::new()
+    );
+    let mut output = String::new();
+    json_stream_parser.feed_into(b"12", &mut output).unwrap();
+    json_stream_parser.finish_into(&mut output).unwrap();
+    assert_eq!(output, "{\"op\":\"add\",\"path\":\"\",\"value\":12}\n");
+}
+
+/// Output written outside of the library : lists completed values with their kind and depth, up to a max depth
+struct DepthOutput {
+    max_depth: usize,
+}
+
+impl ParserOutputTrait for DepthOutput {
+    fn on_init(&mut self, output: &mut String, _current_node_idx: usize, value_kind: Option<ValueKind>) {
+        output.push_str(&format!("init {:?}\n", value_kind));
+    }
+
+    fn on_status_complete(&mut self, output: &mut String, parent_kind: Option<ValueKind>, current_kind: ValueKind, _current_node_idx: usize, key_path: &JsonKeyPath, _output_value: Option<Shared<Value>>) {
+        if current_kind.is_basic() && key_path.depth() <= self.max_depth {
+            output.push_str(&format!("{} {:?} in {:?} at {}\n", key_path.get_current_key(), current_kind, parent_kind, key_path.depth()));
+        }
+    }
+
+    fn on_object_key_complete(&mut self, _output: &mut String, _key: &String, _key_path: &JsonKeyPath) {}
+
+    fn on_flush(&mut self, _output: &mut String, _current_node_idx: usize, _key_path: &JsonKeyPath, _flush_output: &Value) {}
+
+    fn on_node_complete(&mut self, output: &mut String, current_kind: ValueKind, _current_node_idx: usize, key_path: &JsonKeyPath) {
+        if key_path.depth() <= self.max_depth {
+            output.push_str(&format!("{} {:?} complete\n", key_path.get_current_key(), current_kind));
+        }
+    }
+
+    fn on_error(&mut self, _output: &mut String, _message: &str) {}
+
+    fn on_finish(&mut self, _output: &mut String) {}
+
+    fn on_new_subnode(&mut self, _output: &mut String, _parent_node: ParentNode, _current_kind: ValueKind, _parent_node_idx: usize, _current_node_idx: usize, _key_path: &JsonKeyPath) {}
+}
+
+#[test]
+fn test_custom_parser_output() {
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        false,
+        ParserOptions::default(),
+        DepthOutput { max_depth: 1 }
+    );
+    let output = json_stream_parser.feed(br#"{"a": 1, "b": [true, {"c": null}], "d": "x"}"#).unwrap().unwrap();
+    assert_eq!(output, [
+        "init Some(Object)",
+        "a Number in Some(Object) at 1",
+        "b Array complete",
+        "d String complete",
+        " Object complete",
+        "",
+    ].join("\n"));
+}
+
+#[test]
+fn test_tee_and_closure_outputs() {
+    let input = br#"{"a": [1, "xy"], "b": tru"#;
+    let errors = Shared::new(AtomicUsize::new(0));
+    let errors_clone = Shared::clone(&errors);
+    let trace = ClosureOutput::new()
+        .with_on_status_complete(|output, _parent_kind, current_kind, _idx, key_path, _value| {
+            output.push_str(&format!("{} {:?}\n", key_path.to_json_pointer(), current_kind));
+        })
+        .with_on_error(move |output, message| {
+            errors_clone.fetch_add(1, Ordering::Relaxed);
+            output.push_str(message);
+        });
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        false,
+        ParserOptions::default(),
+        TeeOutput::new(vec![Box::new(StreamProtocolOutput::new()), Box::new(trace), Box::new(PathProtocolOutput::new())])
+    );
+    let output = json_stream_parser.feed(input).unwrap().unwrap();
+
+    // The primary output is unchanged
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut single_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        false,
+        ParserOptions::default(),
+        StreamProtocolOutput::new()
+    );
+    assert_eq!(output, single_parser.feed(input).unwrap().unwrap());
+
+    // Secondary outputs are written apart
+    assert_eq!(json_stream_parser.parser_output_mut().take_secondary_output(0).unwrap(), "/a/0 Number\n/a/1 String\n/a Array\n");
+    assert!(json_stream_parser.parser_output().secondary_output(1).unwrap().starts_with(". = {}\na = []\n"));
+    assert!(json_stream_parser.feed(b"x").is_err());
+    assert_eq!(errors.load(Ordering::Relaxed), 1);
+    assert!(!json_stream_parser.parser_output().secondary_output(0).unwrap().is_empty());
+    assert_eq!(json_stream_parser.parser_output().secondary_output(2), None);
+}
+
+#[test]
+fn test_path_protocol_output() {
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        false,
+        ParserOptions::default(),
+        PathProtocolOutput::new()
+    );
+    let mut output = json_stream_parser.feed(br#"{"choices": [{"delta": {"content": "Hel"#).unwrap().unwrap();
+    output.push_str(&json_stream_parser.flush().unwrap());
+    output.push_str(&json_stream_parser.feed(br#"lo"}}], "a.b c": [1, ""], "x\"": null}"#).unwrap().unwrap());
+    assert_eq!(output, [
+        r#". = {}"#,
+        r#"choices = []"#,
+        r#"choices.0 = {}"#,
+        r#"choices.0.delta = {}"#,
+        r#"choices.0.delta.content = """#,
+        r#"choices.0.delta.content += "Hel""#,
+        r#"choices.0.delta.content += "lo""#,
+        r#""a.b c" = []"#,
+        r#""a.b c".0 = 1"#,
+        r#""a.b c".1 = """#,
+        r#""x\"" = null"#,
+        "",
+    ].join("\n"));
+
+    let mut decoder = PathProtocolDecoder::new();
+    decoder.apply_rows(&output).unwrap();
+    assert_eq!(decoder.value(), Some(&json!({"choices": [{"delta": {"content": "Hello"}}], "a.b c": [1, ""], "x\"": null})));
+
+    let mut decoder = PathProtocolDecoder::new();
+    decoder.apply_rows(". = {}\n\"\" = []\n\"\".0 = \"\"\n\"\".0 += \"a\"\n").unwrap();
+    assert_eq!(decoder.value(), Some(&json!({"": ["a"]})));
+
+    // Basic root values
+    let mut decoder = PathProtocolDecoder::new();
+    decoder.apply_row(". = 12").unwrap();
+    assert_eq!(decoder.value(), Some(&json!(12)));
+
+    assert!(decoder.apply_row("a = 1").is_err());
+    assert!(PathProtocolDecoder::new().apply_row("a = 1").is_err());
+    assert!(decoder.apply_row(". 1").is_err());
+}
+
+#[test]
+fn test_element_end_values_and_empty_keys() {
+    let received = Shared::new(Mutex::new(Vec::new()));
+    let received_clone = Shared::clone(&received);
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        RefIndexGenerator::new(),
+        0,
+        true,
+        ParserOptions::default(),
+        StreamProtocolOutput::new()
+    ).with_event_handler(ParserEvent::OnElementEnd, "*".to_string(), Box::new(move |value| {
+        received_clone.lock().unwrap().push(value.map(|value| value.as_ref().clone()));
+    }));
+    // Values following a key are given as they are, never as their key
+    json_stream_parser.feed(br#"{"a": 1, "b": "c", "d": true}"#).unwrap();
+    assert_eq!(*received.lock().unwrap(), vec![Some(json!(1)), Some(json!("c")), Some(json!(true))]);
+
+    // An empty key is a key like any other
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        RefIndexGenerator::new(),
+        0,
+        true,
+        ParserOptions::default(),
+        StreamProtocolOutput::new()
+    );
+    json_stream_parser.feed(br#"{"": 1, "a": {"": "b"}}"#).unwrap();
+    assert_eq!(json_stream_parser.get_buffered_data(), Some(&json!({"": 1, "a": {"": "b"}})));
+}
@@ -4,4 +4,8 @@ mod json_value_pointer;
 mod json_growing_tree_chunker;
 mod json_protocol_chunker;
 mod json_stream_parser;
-mod benchmarks;
\ No newline at end of file
+mod benchmarks;
+mod sync_parser;
+mod input_encoding_decoder;
+mod http_body_decoder;
+mod stream_protocol;
@@ -0,0 +1,60 @@
+// Kept out of tests/mod.rs : the counting allocator below must not slow down the other tests
+use std::{alloc::{GlobalAlloc, Layout, System}, cell::Cell};
+
+use serde_json::Value;
+use stream_protocol_lib::{json_stream_parser::{parser_options::ParserOptions, parser_output::stream_protocol_output::StreamProtocolOutput, JsonStreamParser}, ref_index_generator::RefIndexGenerator, Shared};
+
+/// Counts the bytes allocated and not freed yet by the current thread, so that tests running in parallel do not interfere
+struct CountingAllocator;
+
+thread_local! {
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE_BYTES.with(|live_bytes| live_bytes.set(live_bytes.get() + layout.size() as isize));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.with(|live_bytes| live_bytes.set(live_bytes.get() - layout.size() as isize));
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn live_bytes() -> isize {
+    LIVE_BYTES.with(|live_bytes| live_bytes.get())
+}
+
+#[test]
+fn test_long_array_memory_is_bounded() {
+    // A long stream of small array elements, without buffering : memory used by the parser must not grow with the number of elements
+    let element = |i: usize| format!(r#"{{"id":{i},"name":"element {i}","tags":["a","b"]}},"#);
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        RefIndexGenerator::new(),
+        0,
+        false,
+        ParserOptions::default(),
+        StreamProtocolOutput::new()
+    );
+    let mut output = String::with_capacity(1024);
+    let mut feed_element = |json_stream_parser: &mut JsonStreamParser<_, _>, i: usize| {
+        let input = element(i);
+        output.clear();
+        json_stream_parser.feed_into(input.as_bytes(), &mut output).unwrap();
+    };
+    json_stream_parser.feed(b"[").unwrap();
+    for i in 0..1_000 {
+        feed_element(&mut json_stream_parser, i);
+    }
+    let warmed_up_bytes = live_bytes();
+    for i in 1_000..20_000 {
+        feed_element(&mut json_stream_parser, i);
+    }
+    // Longer indices and numbers may take a few more bytes, but nothing proportional to the 19 000 elements
+    assert!(live_bytes() - warmed_up_bytes < 256, "Parser memory grew by {} bytes", live_bytes() - warmed_up_bytes);
+}
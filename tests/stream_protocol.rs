@@ -0,0 +1,598 @@
+use std::{sync::Mutex, time::Duration};
+
+use futures::StreamExt;
+
+use serde_json::{json, Value};
+use stream_protocol_lib::{chunkers::json_protocol_chunker::JsonProtocolChunker, json_stream_parser::{parser_options::{Clock, ParserOptions, SystemClock}, parser_output::{binary_protocol_output::BinaryProtocolOutput, protocol_row_output::ProtocolRowOutput, replay_output::ReplayOutput, sse_output::SseOutput, stream_protocol_output::StreamProtocolOutput}, JsonStreamParser}, ref_index_generator::RefIndexGenerator, stream_protocol::{binary_row_encoder::{BinaryRowDecoder, BinaryRowEncoder, BINARY_FLAG_KEY, BINARY_FLAG_REFERENCE, BINARY_OP_APPEND, BINARY_OP_ASSIGN}, dialect::{IndexBase, ProtocolDialect, ProtocolString}, protocol_decoder::ProtocolDecoder, protocol_row::{ProtocolRow, RowEncoder, RowValue}, replay_log::{Replay, ReplayLog}, sse::{SseFramer, SseStream, SSE_KEEP_ALIVE_COMMENT}, text_row_encoder::TextRowEncoder, ProtocolHeader, EXTENSION_COMPLETION_MARKERS, EXTENSION_END_ROWS, EXTENSION_ESCAPE, PROTOCOL_VERSION}, Shared};
+
+#[test]
+fn test_protocol_header() {
+    let header = ProtocolHeader::new(3).with_extension(EXTENSION_COMPLETION_MARKERS);
+    let mut row = String::new();
+    header.write_row(&mut row, &ProtocolDialect::default());
+    assert_eq!(row, "!ke={\"version\":1,\"root\":3,\"ext\":[\"close\"]}\n");
+    assert_eq!(ProtocolHeader::parse(&row).unwrap(), header);
+    assert!(header.is_supported());
+    assert!(header.has_extension(EXTENSION_COMPLETION_MARKERS));
+    assert!(!header.has_extension(EXTENSION_END_ROWS));
+
+    // Newer versions and unknown extensions are still readable as a header, letting the consumer decide
+    let header = ProtocolHeader::parse(r#"!ke={"version":2,"root":0,"ext":["future"],"other":true}"#).unwrap();
+    assert_eq!(header.version, PROTOCOL_VERSION + 1);
+    assert!(!header.is_supported());
+    assert!(header.has_extension("future"));
+    assert_eq!(ProtocolHeader::parse(r#"!ke={"version":1,"root":0}"#).unwrap(), ProtocolHeader::new(0));
+    assert_eq!(ProtocolHeader::parse("!ke={\"version\":1,\"root\":0}\r\n").unwrap(), ProtocolHeader::new(0));
+
+    assert!(ProtocolHeader::parse("0={}").is_err());
+    assert!(ProtocolHeader::parse(r#"!ke={"root":0}"#).is_err());
+}
+
+#[test]
+fn test_protocol_header_rows() {
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        5,
+        false,
+        ParserOptions::default(),
+        StreamProtocolOutput::new().with_header().with_end_rows()
+    );
+    let output = json_stream_parser.feed(b" [1]").unwrap().unwrap();
+    assert_eq!(output, "!ke={\"version\":1,\"root\":5,\"ext\":[\"end\"]}\n5=[]\n5+=1\n");
+
+    // The chunker declares the same header
+    let chunker = JsonProtocolChunker::new(json!([1]), RefIndexGenerator::new(), 5).with_header().with_end_row();
+    let chunks = chunker.chunks(2).collect::<Vec<String>>();
+    assert_eq!(chunks, [
+        "!ke={\"version\":1,\"root\":5,\"ext\":[\"end\"]}\n",
+        "5=[]\n",
+        "5+=\"$ke$1\"\n",
+        "1=1\n",
+        "!end\n",
+    ]);
+    assert_eq!(ProtocolHeader::parse(&chunks[0]).unwrap().root, 5);
+}
+
+#[test]
+fn test_protocol_dialect() {
+    let dialect = ProtocolDialect::default().with_escape_literals(true);
+    let mut output = String::new();
+    for literal in ["$ke$5", "$$ke$x", "a$", "$100", "$$"] {
+        dialect.write_literal(&mut output, literal);
+        output.push(' ');
+    }
+    dialect.write_reference(&mut output, 5);
+    // Only the literals that would be read as a reference or as an escaped literal are escaped
+    assert_eq!(output, r#""$$ke$5" "$$$ke$x" "a$" "$100" "$$" "$ke$5""#);
+    assert_eq!(dialect.decode_string("$$ke$5".to_string()).unwrap(), ProtocolString::Literal("$ke$5".to_string()));
+    assert_eq!(dialect.decode_string("$$$ke$x".to_string()).unwrap(), ProtocolString::Literal("$$ke$x".to_string()));
+    assert_eq!(dialect.decode_string("$100".to_string()).unwrap(), ProtocolString::Literal("$100".to_string()));
+    assert_eq!(dialect.decode_string("$$".to_string()).unwrap(), ProtocolString::Literal("$$".to_string()));
+    assert_eq!(dialect.decode_string("$ke$5".to_string()).unwrap(), ProtocolString::Reference(5));
+    assert!(dialect.decode_string("$ke$x".to_string()).is_err());
+    assert!(dialect.decode_string("$ke$05".to_string()).is_err());
+    // Appended strings are string chunks, never escaped
+    assert_eq!(dialect.decode_appended_string("$$ke$5".to_string()), ProtocolString::Literal("$$ke$5".to_string()));
+    assert_eq!(dialect.decode_appended_string("$ke$5".to_string()), ProtocolString::Reference(5));
+
+    // By default nothing is escaped : anything but a reference is a literal
+    let dialect = ProtocolDialect::default();
+    assert_eq!(dialect.decode_string("$other".to_string()).unwrap(), ProtocolString::Literal("$other".to_string()));
+    assert_eq!(dialect.decode_string("$ke$05".to_string()).unwrap(), ProtocolString::Literal("$ke$05".to_string()));
+    assert_eq!(dialect.decode_string("$$ke$5".to_string()).unwrap(), ProtocolString::Literal("$$ke$5".to_string()));
+    for dialect in [ProtocolDialect::default(), ProtocolDialect::default().with_escape_literals(true)] {
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+            RefIndexGenerator::new(),
+            0,
+            false,
+            ParserOptions::default(),
+            StreamProtocolOutput::new().with_dialect(dialect.clone())
+        );
+        let output = json_stream_parser.feed(br#"{"a":"$100"}"#).unwrap().unwrap();
+        assert_eq!(output, "0={}\n0+={\"a\":\"$ke$2\"}\n2=\"\"\n2+=\"$100\"\n");
+        let chunks = JsonProtocolChunker::new(json!("$100"), RefIndexGenerator::new(), 0).with_dialect(dialect).chunks(10).collect::<Vec<String>>();
+        assert_eq!(chunks, ["0=\"$100\"\n"]);
+    }
+
+    // The first character of the prefix must not be repeated, as it is used for escaping
+    assert!(ProtocolDialect::default().with_var_prefix("@r").is_ok());
+    assert!(ProtocolDialect::default().with_var_prefix("@@r").is_err());
+    assert!(ProtocolDialect::default().with_var_prefix("").is_err());
+
+    for (index_base, idx, expected) in [
+        (IndexBase::Decimal, 1234, "1234"),
+        (IndexBase::Base36, 0, "0"),
+        (IndexBase::Base36, 1234, "ya"),
+        (IndexBase::Base62, 1234, "jU"),
+        (IndexBase::Base62, usize::MAX, "lYGhA16ahyf"),
+    ] {
+        let dialect = ProtocolDialect::default().with_index_base(index_base);
+        let mut output = String::new();
+        dialect.write_index(&mut output, idx);
+        assert_eq!(output, expected);
+        assert_eq!(dialect.parse_index(expected), Some(idx));
+    }
+    assert_eq!(ProtocolDialect::default().with_index_base(IndexBase::Base36).parse_index("A"), None);
+    assert_eq!(ProtocolDialect::default().parse_index(""), None);
+}
+
+#[test]
+fn test_protocol_dialect_round_trip() {
+    let source = json!({
+        "$ke$1": "$ke$2",
+        "list": ["$", "$$ke$3", "a$ke$4", ["$ke$5"], {"k": "$x"}],
+        "text": "$ke$ and more text, long enough to be split in several chunks",
+        "price": "$100",
+        "num": 12
+    });
+    let dialects = [
+        ProtocolDialect::default(),
+        ProtocolDialect::default().with_escape_literals(true),
+        ProtocolDialect::default().with_index_base(IndexBase::Base36),
+        ProtocolDialect::default().with_index_base(IndexBase::Base62).with_var_prefix("@r").unwrap().with_row_terminator("\r\n"),
+    ];
+    for dialect in dialects {
+        // Parser output
+        let ref_index_generator = RefIndexGenerator::new();
+        let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+            ref_index_generator,
+            0,
+            false,
+            ParserOptions::default(),
+            StreamProtocolOutput::new().with_header().with_dialect(dialect.clone())
+        );
+        let mut output = String::new();
+        for chunk in source.to_string().as_bytes().chunks(7) {
+            json_stream_parser.feed_into(chunk, &mut output).unwrap();
+            json_stream_parser.flush_into(&mut output);
+        }
+        json_stream_parser.finish_into(&mut output).unwrap();
+        assert!(output.ends_with(&dialect.row_terminator));
+        let mut decoder = ProtocolDecoder::new(ProtocolDialect::default().with_row_terminator(dialect.row_terminator.clone()));
+        decoder.apply_rows(&output).unwrap();
+        assert_eq!(decoder.dialect(), &dialect);
+        assert_eq!(decoder.value(), Some(source.clone()), "{output}");
+
+        // Chunker
+        let chunker = JsonProtocolChunker::new(source.clone(), RefIndexGenerator::new(), 0).with_header().with_dialect(dialect.clone());
+        let mut decoder = ProtocolDecoder::new(ProtocolDialect::default().with_row_terminator(dialect.row_terminator.clone()));
+        for row in chunker.chunks(3) {
+            decoder.apply_row(&row).unwrap();
+        }
+        assert_eq!(decoder.value(), Some(source.clone()));
+    }
+}
+
+#[test]
+fn test_protocol_dialect_nested_literals() {
+    // Strings nested in assigned values are escaped as well
+    let dialect = ProtocolDialect::default().with_escape_literals(true);
+    let encoder = TextRowEncoder::new(dialect.clone());
+    let row = ProtocolRow::assign(3, json!({"$ke$1": ["$ke$5", {"b": "$$ke$6"}], "c": "$x"}));
+    let mut output = String::new();
+    encoder.encode_row(&row, &mut output);
+    assert_eq!(output, "3={\"$ke$1\":[\"$$ke$5\",{\"b\":\"$$$ke$6\"}],\"c\":\"$x\"}\n");
+    assert_eq!(encoder.decode_row(&output).unwrap(), row);
+    let row = ProtocolRow::append(3, RowValue::KeyValue("a".to_string(), Shared::new(json!(["$ke$5"]))));
+    output.clear();
+    encoder.encode_row(&row, &mut output);
+    assert_eq!(output, "3+={\"a\":[\"$$ke$5\"]}\n");
+    assert_eq!(encoder.decode_row(&output).unwrap(), row);
+
+    // Including the containers of resync rows
+    let document = r#"{"a": ["$ke$5", {"b": "$$ke$1"}], "c": "$ke$2"}"#;
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        RefIndexGenerator::new(),
+        0,
+        true,
+        ParserOptions::default(),
+        StreamProtocolOutput::new().with_dialect(dialect.clone())
+    );
+    json_stream_parser.feed(&document.as_bytes()[..document.len() - 4]).unwrap();
+    json_stream_parser.flush();
+    let mut resync = String::new();
+    for row in json_stream_parser.resync_rows().unwrap() {
+        encoder.encode_row(&row, &mut resync);
+    }
+    assert!(resync.starts_with("0={\"a\":[\"$$ke$5\",{\"b\":\"$$$ke$1\"}]}\n"), "{resync}");
+    let mut decoder = ProtocolDecoder::new(dialect);
+    decoder.apply_rows(&resync).unwrap();
+    let mut output = json_stream_parser.feed(&document.as_bytes()[document.len() - 4..]).unwrap().unwrap_or_default();
+    json_stream_parser.finish_into(&mut output).unwrap();
+    decoder.apply_rows(&output).unwrap();
+    assert_eq!(decoder.value(), Some(serde_json::from_str::<Value>(document).unwrap()));
+
+    // By default nothing is escaped : a literal looking like a reference is read as one
+    let encoder = TextRowEncoder::default();
+    let mut literal = String::new();
+    encoder.encode_row(&ProtocolRow::append(3, RowValue::KeyValue("a".to_string(), Shared::new(json!("$ke$5")))), &mut literal);
+    let mut reference = String::new();
+    encoder.encode_row(&ProtocolRow::append(3, RowValue::KeyReference("a".to_string(), 5)), &mut reference);
+    assert_eq!(literal, reference);
+    assert_eq!(encoder.decode_row(&literal).unwrap(), ProtocolRow::append(3, RowValue::KeyReference("a".to_string(), 5)));
+    // The header tells consumers whether literals are escaped
+    assert!(!ProtocolHeader::new(0).with_dialect(&ProtocolDialect::default()).has_extension(EXTENSION_ESCAPE));
+    let header = ProtocolHeader::new(0).with_dialect(&ProtocolDialect::default().with_escape_literals(true));
+    assert!(header.has_extension(EXTENSION_ESCAPE));
+    assert!(header.dialect().unwrap().escape_literals);
+}
+
+#[test]
+fn test_protocol_decoder() {
+    let mut decoder = ProtocolDecoder::new(ProtocolDialect::default());
+    decoder.apply_rows("0={}\n0+={\"a\":\"$ke$1\"}\n1=\"\"\n1+=\"$ke$\"\n1+=\"2\"\n1!\n").unwrap();
+    assert_eq!(decoder.value(), Some(json!({"a": "$ke$2"})));
+    assert!(decoder.is_node_closed(1));
+    assert!(!decoder.is_node_closed(0));
+    assert!(!decoder.is_finished());
+    decoder.apply_row("!error={\"message\":\"failure\"}").unwrap();
+    assert_eq!(decoder.error(), Some("failure"));
+
+    // Unknown nodes and operators
+    assert!(decoder.apply_row("7+=1").is_err());
+    assert!(decoder.apply_row("0*=1").is_err());
+    assert!(decoder.apply_row("1+=5").is_err());
+    assert!(decoder.apply_row(r#"!ke={"version":2,"root":0}"#).is_err());
+    assert!(decoder.apply_row(r#"!ke={"version":1,"root":0,"prefix":"$$"}"#).is_err());
+
+    // References to nodes not received yet are null
+    let mut decoder = ProtocolDecoder::new(ProtocolDialect::default());
+    decoder.apply_rows("0=[]\n0+=\"$ke$1\"\n0+=\"$ke$0\"\n").unwrap();
+    assert_eq!(decoder.value(), Some(json!([null, null])));
+
+    // A reference is never assigned : such a string is a literal
+    let mut decoder = ProtocolDecoder::new(ProtocolDialect::default());
+    decoder.apply_rows("0=\"$ke$1\"\n").unwrap();
+    assert_eq!(decoder.value(), Some(json!("$ke$1")));
+}
+
+#[test]
+fn test_protocol_rows() {
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        false,
+        ParserOptions::default(),
+        ProtocolRowOutput::new().with_completion_markers()
+    );
+    // The text output is left empty : rows are taken from the output
+    assert_eq!(json_stream_parser.feed(br#"{"a":1,"b":["$x"]}"#).unwrap(), None);
+    let rows = json_stream_parser.parser_output_mut().take_rows();
+    assert_eq!(rows, [
+        ProtocolRow::assign(0, json!({})),
+        ProtocolRow::append(0, RowValue::KeyValue("a".to_string(), Shared::new(json!(1)))),
+        ProtocolRow::append(0, RowValue::KeyReference("b".to_string(), 4)),
+        ProtocolRow::assign(4, json!([])),
+        ProtocolRow::append(4, RowValue::Reference(5)),
+        ProtocolRow::assign(5, json!("")),
+        ProtocolRow::append(5, RowValue::Value(Shared::new(json!("$x")))),
+        ProtocolRow::close(5),
+        ProtocolRow::close(4),
+        ProtocolRow::close(0),
+    ]);
+    assert!(json_stream_parser.parser_output().rows().is_empty());
+
+    // Rows are serializable as is, and their text form is the one of StreamProtocolOutput
+    let serialized = serde_json::to_value(&rows[2]).unwrap();
+    assert_eq!(serialized, json!({"idx": 0, "op": "append", "value": {"key_reference": ["b", 4]}}));
+    assert_eq!(serde_json::from_value::<ProtocolRow>(serialized).unwrap(), rows[2]);
+
+    let encoder = TextRowEncoder::default();
+    let mut text = String::new();
+    for row in &rows {
+        encoder.encode_row(row, &mut text);
+    }
+    assert_eq!(text, "0={}\n0+={\"a\":1}\n0+={\"b\":\"$ke$4\"}\n4=[]\n4+=\"$ke$5\"\n5=\"\"\n5+=\"$x\"\n5!\n4!\n0!\n");
+    let decoded = text.lines().map(|row| encoder.decode_row(row).unwrap()).collect::<Vec<ProtocolRow>>();
+    assert_eq!(decoded, rows);
+
+    // Rows handed to the encoder as they are produced are written straight into the output
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        RefIndexGenerator::new(),
+        0,
+        false,
+        ParserOptions::default(),
+        ProtocolRowOutput::with_sink(TextRowEncoder::default()).with_completion_markers()
+    );
+    assert_eq!(json_stream_parser.feed(br#"{"a":1,"b":["$x"]}"#).unwrap().unwrap(), text);
+
+    for row in [ProtocolRow::header(ProtocolHeader::new(0)), ProtocolRow::end(), ProtocolRow::error("Failed")] {
+        let mut text = String::new();
+        encoder.encode_row(&row, &mut text);
+        let decoded = encoder.decode_row(&text).unwrap();
+        match row.value {
+            // The header declares the dialect once encoded
+            RowValue::Header(header) => assert_eq!(decoded.value, RowValue::Header(encoder.complete_header(&header))),
+            _ => assert_eq!(decoded, row),
+        }
+    }
+    assert!(encoder.decode_row("0+={\"a\":1,\"b\":2}").is_err());
+    assert_eq!(encoder.decode_row("0=\"$ke$1\"").unwrap(), ProtocolRow::assign(0, json!("$ke$1")));
+
+    // The chunker yields the same rows before encoding
+    let chunker = JsonProtocolChunker::new(json!({"b": ["x"]}), RefIndexGenerator::new(), 0).with_end_row();
+    let chunk_rows = chunker.rows(10).collect::<Vec<ProtocolRow>>();
+    assert_eq!(chunk_rows, [
+        ProtocolRow::assign(0, json!({})),
+        ProtocolRow::append(0, RowValue::KeyReference("b".to_string(), 1)),
+        ProtocolRow::assign(1, json!([])),
+        ProtocolRow::append(1, RowValue::Reference(2)),
+        ProtocolRow::assign(2, json!("x")),
+        ProtocolRow::end(),
+    ]);
+    let mut decoder = ProtocolDecoder::new(ProtocolDialect::default());
+    for row in chunk_rows {
+        decoder.apply(row).unwrap();
+    }
+    assert_eq!(decoder.value(), Some(json!({"b": ["x"]})));
+    assert!(decoder.is_finished());
+}
+
+#[derive(Debug, Default)]
+struct SseClock {
+    now: Mutex<Duration>,
+}
+
+impl SseClock {
+    fn set(&self, now: Duration) {
+        *self.now.lock().unwrap() = now;
+    }
+}
+
+impl Clock for SseClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[test]
+fn test_sse_framing() {
+    let ref_index_generator = RefIndexGenerator::new();
+    let framer = SseFramer::new().with_first_id(7).with_event_name("rows");
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        false,
+        ParserOptions::default(),
+        SseOutput::new(StreamProtocolOutput::new(), framer)
+    );
+    let output = json_stream_parser.feed(br#"["a"]"#).unwrap().unwrap();
+    assert_eq!(output, [
+        "id: 7\nevent: rows\ndata: 0=[]\n\n",
+        "id: 8\nevent: rows\ndata: 0+=\"$ke$1\"\n\n",
+        "id: 9\nevent: rows\ndata: 1=\"\"\n\n",
+        "id: 10\nevent: rows\ndata: 1+=\"a\"\n\n",
+    ].concat());
+    assert_eq!(json_stream_parser.parser_output().framer().next_id(), 11);
+
+    // Batches of rows, as returned by the parser, are framed as a single event
+    let mut framer = SseFramer::new();
+    let mut output = String::new();
+    assert_eq!(framer.write_event(&mut output, "0={}\r\n0+={\"a\":1}\r\n"), Some(0));
+    assert_eq!(framer.write_event(&mut output, ""), None);
+    assert_eq!(output, "id: 0\ndata: 0={}\ndata: 0+={\"a\":1}\n\n");
+
+    // Rows of a dialect with another row terminator are still one event each
+    let mut framer = SseFramer::new().with_row_terminator(";");
+    let mut output = String::new();
+    framer.write_row_events(&mut output, "0={};0+={\"a\":1};");
+    assert_eq!(output, "id: 0\ndata: 0={}\n\nid: 1\ndata: 0+={\"a\":1}\n\n");
+
+    // Keep-alive comments, once idle for the interval
+    let clock = Shared::new(SseClock::default());
+    let mut framer = SseFramer::new().with_keep_alive(Duration::from_secs(15), clock.clone());
+    let mut output = String::new();
+    clock.set(Duration::from_secs(10));
+    assert!(!framer.keep_alive_if_idle(&mut output));
+    framer.write_event(&mut output, "0=1\n");
+    clock.set(Duration::from_secs(20));
+    assert!(!framer.keep_alive_if_idle(&mut output));
+    clock.set(Duration::from_secs(25));
+    assert!(framer.keep_alive_if_idle(&mut output));
+    assert!(!framer.keep_alive_if_idle(&mut output));
+    assert_eq!(output, format!("id: 0\ndata: 0=1\n\n{SSE_KEEP_ALIVE_COMMENT}"));
+
+    // Chunk streams are framed the same way
+    let stream = JsonProtocolChunker::new(json!({"a": 1}), RefIndexGenerator::new(), 0).stream(10, 0).sse(SseFramer::new());
+    let events = futures::executor::block_on(stream.collect::<Vec<String>>());
+    assert_eq!(events, [
+        "id: 0\ndata: 0={}\n\n",
+        "id: 1\ndata: 0+={\"a\":\"$ke$1\"}\n\n",
+        "id: 2\ndata: 1=1\n\n",
+    ]);
+
+    // While the upstream stays pending, the stream wakes up by itself once a keep-alive comment is due
+    let framer = SseFramer::new().with_keep_alive(Duration::from_millis(20), Shared::new(SystemClock::new()));
+    let mut stream = SseStream::new(futures::stream::pending::<String>(), framer);
+    assert_eq!(futures::executor::block_on(stream.next()), Some(SSE_KEEP_ALIVE_COMMENT.to_string()));
+
+    // Chunk streams wait between rows without blocking the thread
+    let mut stream = JsonProtocolChunker::new(json!({"a": 1}), RefIndexGenerator::new(), 0).stream(10, 20);
+    futures::executor::block_on(async {
+        assert!(futures::poll!(stream.next()).is_pending());
+        assert_eq!(stream.collect::<Vec<String>>().await, ["0={}\n", "0+={\"a\":\"$ke$1\"}\n", "1=1\n"]);
+    });
+}
+
+#[test]
+fn test_replay_log() {
+    let mut replay_log = ReplayLog::new(3);
+    replay_log.record_rows("0=[]\n0+=1\n");
+    assert_eq!(replay_log.next_seq(), 2);
+    assert_eq!(replay_log.replay_from(1), Replay::Rows(vec![(1, "0+=1\n".to_string())]));
+    assert_eq!(replay_log.replay_from(2), Replay::Rows(vec![]));
+    assert_eq!(replay_log.replay_from(3), Replay::Resync);
+    replay_log.record_rows("0+=2\n0+=3\n");
+    assert_eq!(replay_log.first_seq(), 1);
+    assert_eq!(replay_log.replay_from(0), Replay::Resync);
+    assert_eq!(replay_log.replay_from(2), Replay::Rows(vec![(2, "0+=2\n".to_string()), (3, "0+=3\n".to_string())]));
+
+    // Bounded by size as well
+    let mut replay_log = ReplayLog::new(10).with_max_bytes(8).with_first_seq(5);
+    replay_log.record_rows("0=\"\"\n0+=\"abc\"\n");
+    assert_eq!(replay_log.len(), 0);
+    assert_eq!(replay_log.replay_from(5), Replay::Resync);
+    assert_eq!(replay_log.replay_from(7), Replay::Rows(vec![]));
+
+    // Rows of the parser, as written to the client
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        false,
+        ParserOptions::default(),
+        ProtocolRowOutput::with_sink(ReplayOutput::new(TextRowEncoder::default(), ReplayLog::new(100)))
+    );
+    let output = json_stream_parser.feed(br#"{"a": ["b"]}"#).unwrap().unwrap();
+    let replay_log = json_stream_parser.parser_output().sink().replay_log();
+    assert_eq!(replay_log.next_seq(), output.lines().count() as u64);
+    match replay_log.replay_from(0) {
+        Replay::Rows(rows) => assert_eq!(rows.into_iter().map(|(_, row)| row).collect::<String>(), output),
+        Replay::Resync => panic!("Rows should be kept"),
+    }
+
+    // Framed as SSE events of several lines, rows are still recorded one by one, matching the event ids
+    let ref_index_generator = RefIndexGenerator::new();
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        ref_index_generator,
+        0,
+        false,
+        ParserOptions::default(),
+        SseOutput::new(ProtocolRowOutput::with_sink(ReplayOutput::new(TextRowEncoder::default(), ReplayLog::new(100))), SseFramer::new())
+    );
+    json_stream_parser.feed(br#"{"a": ["b"]}"#).unwrap().unwrap();
+    let replay_log = json_stream_parser.parser_output().inner().sink().replay_log();
+    assert_eq!(replay_log.next_seq(), json_stream_parser.parser_output().framer().next_id());
+    assert_eq!(replay_log.replay_from(2), Replay::Rows(vec![(2, "2=[]\n".to_string()), (3, "2+=\"$ke$3\"\n".to_string()), (4, "3=\"\"\n".to_string()), (5, "3+=\"b\"\n".to_string())]));
+
+    // Rows of the chunker
+    let mut chunks = JsonProtocolChunker::new(json!({"a": 1}), RefIndexGenerator::new(), 0).chunks(10).with_replay_log(ReplayLog::new(2));
+    let rows = chunks.by_ref().collect::<Vec<String>>();
+    let replay_log = chunks.replay_log().unwrap();
+    assert_eq!(replay_log.replay_from(1), Replay::Rows(vec![(1, rows[1].clone()), (2, rows[2].clone())]));
+    assert_eq!(replay_log.replay_from(0), Replay::Resync);
+}
+
+#[test]
+fn test_resync_rows() {
+    let new_parser = || -> JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> {
+        JsonStreamParser::new(
+            RefIndexGenerator::new(),
+            0,
+            true,
+            ParserOptions::default(),
+            StreamProtocolOutput::new().with_completion_markers().with_end_rows()
+        )
+    };
+    let encoder = TextRowEncoder::default();
+
+    let mut json_stream_parser = new_parser();
+    json_stream_parser.feed(br#"{"a": [1, "bc", {"d": true}], "e": ["fg"#).unwrap();
+    json_stream_parser.flush();
+    let rows = json_stream_parser.resync_rows().unwrap();
+    assert_eq!(rows, vec![
+        ProtocolRow::assign(0, json!({"a": [1, "bc", {"d": true}]})),
+        ProtocolRow::append(0, RowValue::KeyReference("e".to_string(), 9)),
+        ProtocolRow::assign(9, json!([])),
+        ProtocolRow::append(9, RowValue::Reference(10)),
+        ProtocolRow::assign(10, json!("fg")),
+    ]);
+
+    // Joining at any point, then following the live rows, gives the whole document
+    let document = r#"{"a": [1, "b$ke$1", {"d": [true, -2.5e3]}], "e": {"f": "gh", "i": 10}, "j": []}"#;
+    for position in 0..=document.len() {
+        let mut json_stream_parser = new_parser();
+        json_stream_parser.feed(document[..position].as_bytes()).unwrap();
+        json_stream_parser.flush();
+        let mut decoder = ProtocolDecoder::new(ProtocolDialect::default());
+        let mut resync = String::new();
+        for row in json_stream_parser.resync_rows().unwrap() {
+            encoder.encode_row(&row, &mut resync);
+        }
+        decoder.apply_rows(&resync).unwrap();
+        let mut output = json_stream_parser.feed(document[position..].as_bytes()).unwrap().unwrap_or_default();
+        json_stream_parser.finish_into(&mut output).unwrap();
+        decoder.apply_rows(&output).unwrap();
+        assert_eq!(decoder.value(), Some(serde_json::from_str::<Value>(document).unwrap()), "Joining at {position}");
+        assert!(decoder.is_finished());
+    }
+
+    // Without buffering, the state of the document is unknown
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        RefIndexGenerator::new(),
+        0,
+        false,
+        ParserOptions::default(),
+        StreamProtocolOutput::new()
+    );
+    json_stream_parser.feed(b"[1").unwrap();
+    assert_eq!(json_stream_parser.resync_rows(), None);
+}
+
+#[test]
+fn test_binary_rows() {
+    let encoder = BinaryRowEncoder::new();
+    let mut bytes = Vec::new();
+    encoder.encode_row(&ProtocolRow::append(123, RowValue::KeyReference("key".to_string(), 124)), &mut bytes);
+    assert_eq!(bytes, [BINARY_OP_APPEND | BINARY_FLAG_KEY | BINARY_FLAG_REFERENCE, 123, 3, b'k', b'e', b'y', 124]);
+    // Indices above 127 take several bytes, and strings looking like references stay literal
+    bytes.clear();
+    let row = ProtocolRow::append(300, RowValue::Value(Shared::new(json!("$ke$1"))));
+    encoder.encode_row(&row, &mut bytes);
+    assert_eq!(bytes[..3], [BINARY_OP_APPEND, 0xac, 0x02]);
+    assert_eq!(encoder.decode_row(&bytes).unwrap(), Some((row, bytes.len())));
+    assert_eq!(encoder.decode_row(&bytes[..bytes.len() - 1]).unwrap(), None);
+    assert!(encoder.decode_row(&[0xff]).is_err());
+    assert!(encoder.decode_row(&[BINARY_OP_ASSIGN | BINARY_FLAG_REFERENCE, 0, 1]).is_err());
+
+    // Round trip with the text form
+    let document = br#"{"a": [1, "bc", {"d": null}], "e": "fgh", "i": {}}"#;
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        RefIndexGenerator::new(),
+        0,
+        false,
+        ParserOptions::default(),
+        ProtocolRowOutput::new().with_header().with_completion_markers().with_end_rows()
+    );
+    json_stream_parser.feed(document).unwrap();
+    json_stream_parser.finish_into(&mut String::new()).unwrap();
+    let rows = json_stream_parser.parser_output_mut().take_rows();
+    let text_encoder = TextRowEncoder::default();
+    let mut text = String::new();
+    let mut bytes = Vec::new();
+    for row in &rows {
+        text_encoder.encode_row(row, &mut text);
+        encoder.encode_row(row, &mut bytes);
+    }
+    assert!(bytes.len() < text.len());
+    let text_rows = text.lines().map(|row| text_encoder.decode_row(row).unwrap()).collect::<Vec<ProtocolRow>>();
+    let mut binary_decoder = BinaryRowDecoder::new();
+    let mut binary_rows = Vec::new();
+    for byte in &bytes {
+        binary_rows.extend(binary_decoder.feed(std::slice::from_ref(byte)).unwrap());
+    }
+    assert!(!binary_decoder.has_pending());
+    assert_eq!(binary_rows, rows);
+    assert_eq!(text_rows[1..], binary_rows[1..]); // The text header also declares the dialect
+
+    let mut decoder = ProtocolDecoder::new(ProtocolDialect::default());
+    for row in binary_rows {
+        decoder.apply(row).unwrap();
+    }
+    assert_eq!(decoder.value(), Some(serde_json::from_slice::<Value>(document).unwrap()));
+    assert!(decoder.is_finished());
+
+    // Written directly by the parser
+    let mut json_stream_parser: JsonStreamParser<Box<dyn Fn(Option<Shared<Value>>)>, _> = JsonStreamParser::new(
+        RefIndexGenerator::new(),
+        0,
+        false,
+        ParserOptions::default(),
+        BinaryProtocolOutput::new().with_header().with_completion_markers().with_end_rows()
+    );
+    assert_eq!(json_stream_parser.feed(document).unwrap(), None);
+    json_stream_parser.finish_into(&mut String::new()).unwrap();
+    assert_eq!(json_stream_parser.parser_output_mut().take_bytes(), bytes);
+}
@@ -0,0 +1,61 @@
+// Only relevant when the "sync" feature is enabled : cargo test --features sync
+#![cfg(feature = "sync")]
+
+use std::{collections::HashSet, sync::{Arc, Mutex}, thread};
+
+use serde_json::json;
+use stream_protocol_lib::{json_stream_parser::{parser_options::ParserOptions, parser_output::{stream_protocol_output::StreamProtocolOutput}, BoxedEventHandler, JsonStreamParser, ParserEvent}, ref_index_generator::RefIndexGenerator};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn test_sync_parser_is_send_sync() {
+    assert_send_sync::<RefIndexGenerator>();
+    assert_send_sync::<JsonStreamParser<BoxedEventHandler, StreamProtocolOutput>>();
+}
+
+#[test]
+fn test_sync_parser_across_threads() {
+    let ref_index_generator = RefIndexGenerator::new();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_copy = Arc::clone(&received);
+    let mut json_stream_parser: JsonStreamParser<BoxedEventHandler, _> = JsonStreamParser::new(
+        ref_index_generator.clone(),
+        0,
+        true,
+        ParserOptions::default(),
+        StreamProtocolOutput::new()
+    ).with_event_handler(ParserEvent::OnElementEnd, "a".to_string(), Box::new(move |value| {
+        received_copy.lock().unwrap().push(value.map(|v| v.as_ref().clone()));
+    }));
+
+    let output = thread::spawn(move || {
+        let mut output = String::new();
+        for byte in r#"{"a":"b"}"#.as_bytes() {
+            if let Some(row) = json_stream_parser.add_char(byte).unwrap() {
+                output.push_str(&row);
+            }
+        }
+        assert_eq!(json_stream_parser.get_buffered_data(), Some(&json!({"a": "b"})));
+        output
+    }).join().unwrap();
+
+    assert_eq!(output, "0={}\n0+={\"a\":\"$ke$2\"}\n2=\"\"\n2+=\"b\"\n");
+    assert_eq!(*received.lock().unwrap(), vec![Some(json!("b"))]);
+}
+
+#[test]
+fn test_sync_ref_index_generator_unique() {
+    let ref_index_generator = RefIndexGenerator::new();
+    let handles: Vec<_> = (0..4).map(|_| {
+        let generator = ref_index_generator.clone();
+        thread::spawn(move || (0..1000).map(|_| generator.generate()).collect::<Vec<usize>>())
+    }).collect();
+    let mut all_indices = HashSet::new();
+    for handle in handles {
+        for idx in handle.join().unwrap() {
+            assert!(all_indices.insert(idx));
+        }
+    }
+    assert_eq!(all_indices.len(), 4000);
+}